@@ -0,0 +1,256 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+use std::fmt::Write;
+
+/// Values made available to evaluated code as typed variables.
+///
+/// Each binding is injected before the code as a `let` statement declaring
+/// the type of the variable. Values are written as Rust expressions by
+/// `Literal`, so strings and characters are escaped.
+///
+/// # Examples
+///
+/// ```rust
+/// use everust::{Bindings, Evaluator};
+/// let bindings = Bindings::new()
+///     .bind("x", 21)
+///     .bind("name", "world".to_string());
+/// let output = Evaluator::new()
+///     .eval_with_bindings(r#"format!("{} {}", name, x * 2)"#, &bindings)
+///     .unwrap();
+/// assert_eq!(r#""world 42""#, output.value);
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Bindings {
+    bindings: Vec<Binding>,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct Binding {
+    name: String,
+    ty: String,
+    value: String,
+}
+
+impl Bindings {
+    /// Returns an empty set of bindings.
+    pub fn new() -> Bindings {
+        Bindings::default()
+    }
+
+    /// Adds a variable named `name` with the given value.
+    ///
+    /// A later binding with the same name shadows the earlier one. The name
+    /// must be a valid identifier, or evaluating the code fails with
+    /// `EvalError::Build`.
+    pub fn bind<S, T>(mut self, name: S, value: T) -> Bindings
+    where
+        S: Into<String>,
+        T: Literal,
+    {
+        let mut literal = String::new();
+        value.write_literal(&mut literal);
+        self.bindings.push(Binding {
+            name: name.into(),
+            ty: T::type_name(),
+            value: literal,
+        });
+        self
+    }
+
+    /// Returns whether there are no bindings.
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+
+    /// Returns the `let` statements declaring the variables.
+    pub(crate) fn to_source(&self) -> Result<String, String> {
+        let mut source = String::new();
+        for binding in &self.bindings {
+            if !is_identifier(&binding.name) {
+                return Err(format!("Invalid binding name: {:?}",
+                    binding.name))
+            }
+            writeln!(source, "    let {}: {} = {};", binding.name, binding.ty,
+                binding.value).unwrap();
+        }
+        Ok(source)
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    let valid_start = match chars.next() {
+        Some(c) => c == '_' || c.is_ascii_alphabetic(),
+        None => false,
+    };
+    valid_start && name != "_"
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+        && !KEYWORDS.contains(&name)
+}
+
+const KEYWORDS: &[&str] = &["abstract", "as", "async", "await", "become",
+    "box", "break", "const", "continue", "crate", "do", "dyn", "else", "enum",
+    "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+    "true", "try", "type", "typeof", "union", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield"];
+
+/// Type whose values can be written as Rust expressions, to be bound with
+/// `Bindings::bind`.
+pub trait Literal {
+    /// Returns the name of the type in the evaluated code.
+    fn type_name() -> String;
+
+    /// Writes an expression evaluating to the value.
+    fn write_literal(&self, out: &mut String);
+}
+
+macro_rules! debug_literal {
+    ($($t:ty),*) => {$(
+        impl Literal for $t {
+            fn type_name() -> String {
+                stringify!($t).to_owned()
+            }
+
+            fn write_literal(&self, out: &mut String) {
+                write!(out, "{:?}", self).unwrap();
+            }
+        }
+    )*};
+}
+
+debug_literal!(bool, char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64,
+    u128, usize);
+
+macro_rules! float_literal {
+    ($($t:ident),*) => {$(
+        impl Literal for $t {
+            fn type_name() -> String {
+                stringify!($t).to_owned()
+            }
+
+            fn write_literal(&self, out: &mut String) {
+                if self.is_nan() {
+                    out.push_str(concat!(stringify!($t), "::NAN"));
+                } else if self.is_infinite() && *self > 0.0 {
+                    out.push_str(concat!(stringify!($t), "::INFINITY"));
+                } else if self.is_infinite() {
+                    out.push_str(concat!(stringify!($t), "::NEG_INFINITY"));
+                } else {
+                    write!(out, "{:?}", self).unwrap();
+                }
+            }
+        }
+    )*};
+}
+
+float_literal!(f32, f64);
+
+impl Literal for String {
+    fn type_name() -> String {
+        "::std::string::String".to_owned()
+    }
+
+    fn write_literal(&self, out: &mut String) {
+        write!(out, "::std::string::String::from({:?})", self).unwrap();
+    }
+}
+
+impl Literal for &str {
+    fn type_name() -> String {
+        "&'static str".to_owned()
+    }
+
+    fn write_literal(&self, out: &mut String) {
+        write!(out, "{:?}", self).unwrap();
+    }
+}
+
+impl Literal for () {
+    fn type_name() -> String {
+        "()".to_owned()
+    }
+
+    fn write_literal(&self, out: &mut String) {
+        out.push_str("()");
+    }
+}
+
+impl<T: Literal> Literal for Option<T> {
+    fn type_name() -> String {
+        format!("::std::option::Option<{}>", T::type_name())
+    }
+
+    fn write_literal(&self, out: &mut String) {
+        match *self {
+            Some(ref value) => {
+                out.push_str("::std::option::Option::Some(");
+                value.write_literal(out);
+                out.push(')');
+            }
+            None => out.push_str("::std::option::Option::None"),
+        }
+    }
+}
+
+impl<T: Literal> Literal for Vec<T> {
+    fn type_name() -> String {
+        format!("::std::vec::Vec<{}>", T::type_name())
+    }
+
+    fn write_literal(&self, out: &mut String) {
+        out.push_str("::std::vec::Vec::from(");
+        write_array(self, out);
+        out.push(')');
+    }
+}
+
+impl<T: Literal, const N: usize> Literal for [T; N] {
+    fn type_name() -> String {
+        format!("[{}; {}]", T::type_name(), N)
+    }
+
+    fn write_literal(&self, out: &mut String) {
+        write_array(self, out);
+    }
+}
+
+fn write_array<T: Literal>(values: &[T], out: &mut String) {
+    out.push('[');
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        value.write_literal(out);
+    }
+    out.push(']');
+}
+
+macro_rules! tuple_literal {
+    ($($t:ident $i:tt),+) => {
+        impl<$($t: Literal),+> Literal for ($($t,)+) {
+            fn type_name() -> String {
+                let names = [$($t::type_name()),+];
+                format!("({},)", names.join(", "))
+            }
+
+            fn write_literal(&self, out: &mut String) {
+                out.push('(');
+                $(
+                    self.$i.write_literal(out);
+                    out.push_str(", ");
+                )+
+                out.push(')');
+            }
+        }
+    };
+}
+
+tuple_literal!(A 0);
+tuple_literal!(A 0, B 1);
+tuple_literal!(A 0, B 1, C 2);
+tuple_literal!(A 0, B 1, C 2, D 3);
+tuple_literal!(A 0, B 1, C 2, D 3, E 4);
+tuple_literal!(A 0, B 1, C 2, D 3, E 4, F 5);
@@ -0,0 +1,150 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tempdir::TempDir;
+
+/// Name of the lock file in the cache directory.
+const LOCK_FILE_NAME: &str = ".lock";
+
+/// On-disk cache of compiled programs.
+///
+/// Programs are stored under a hash of the generated source, the build
+/// options and the output of `rustc -vV` (and `cargo -vV` with the Cargo
+/// backend), so that evaluating the same code again skips the build. Only
+/// successful builds are cached.
+///
+/// The cache directory can be shared by several evaluators and processes.
+/// Entries are written atomically, and when the cache exceeds its maximum
+/// size, the least recently used entries are removed.
+///
+/// With the Cargo backend, the key contains the paths of path dependencies
+/// and of the vendor directory resolved to absolute paths, and the size and
+/// modification time of their files, so that local crates are rebuilt when
+/// they change.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use everust::{Cache, Evaluator};
+/// let cache = Cache::new("/var/cache/everust").max_size(1 << 30);
+/// let evaluator = Evaluator::builder().cache(cache).build();
+/// evaluator.eval("1 + 1").unwrap();
+/// assert!(evaluator.eval("1 + 1").unwrap().cached);
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Cache {
+    dir: PathBuf,
+    max_size: Option<u64>,
+}
+
+impl Cache {
+    /// Returns a cache stored in `dir`, without size limit.
+    ///
+    /// The directory is created if needed.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Cache {
+        Cache {dir: dir.into(), max_size: None}
+    }
+
+    /// Sets the maximum total size of the cached programs in bytes.
+    pub fn max_size(mut self, size: u64) -> Cache {
+        self.max_size = Some(size);
+        self
+    }
+
+    /// Returns the cache directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Copies the program cached under `key` to `dest`. Returns `false` if
+    /// there is no such program.
+    pub(crate) fn get(&self, key: &str, dest: &Path) -> io::Result<bool> {
+        let _lock = self.lock(false)?;
+        let entry = self.dir.join(key);
+        match fs::copy(&entry, dest) {
+            Ok(_) => {}
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok(false)
+            }
+            Err(e) => return Err(e),
+        }
+        // The modification time records the last use for eviction.
+        File::options().write(true).open(&entry)?
+            .set_modified(SystemTime::now())?;
+        Ok(true)
+    }
+
+    /// Stores a program under `key`, evicting old entries if the cache is
+    /// too large.
+    pub(crate) fn put(&self, key: &str, program: &Path) -> io::Result<()> {
+        let temp = TempDir::new_in(self.create_dir()?, ".tmp")?;
+        let temp_path = temp.path().join(key);
+        fs::copy(program, &temp_path)?;
+        fs::rename(&temp_path, self.dir.join(key))?;
+        match self.max_size {
+            Some(max_size) => self.evict(max_size),
+            None => Ok(()),
+        }
+    }
+
+    fn evict(&self, max_size: u64) -> io::Result<()> {
+        let _lock = self.lock(true)?;
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue
+            }
+            let metadata = entry.metadata()?;
+            entries.push((metadata.modified()?, metadata.len(), entry.path()));
+        }
+        entries.sort();
+        let mut size = entries.iter().map(|&(_, len, _)| len).sum::<u64>();
+        for (_, len, path) in entries {
+            if size <= max_size {
+                break
+            }
+            match fs::remove_file(path) {
+                Ok(()) => {}
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+            size -= len;
+        }
+        Ok(())
+    }
+
+    fn create_dir(&self) -> io::Result<&Path> {
+        fs::create_dir_all(&self.dir)?;
+        Ok(&self.dir)
+    }
+
+    /// Locks the cache. Entries are only removed with an exclusive lock.
+    fn lock(&self, exclusive: bool) -> io::Result<File> {
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.create_dir()?.join(LOCK_FILE_NAME))?;
+        if exclusive {
+            file.lock()?;
+        } else {
+            file.lock_shared()?;
+        }
+        Ok(file)
+    }
+}
+
+/// Returns the cache key of data made of several parts.
+pub fn key<'a, I: IntoIterator<Item = &'a [u8]>>(parts: I) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,88 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+use crate::output::EvalOutput;
+use crate::EvalError;
+use std::panic;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+
+/// Token used to cancel an evaluation from another thread.
+///
+/// Cancelling kills whichever of rustc (or cargo) and the program is
+/// running, along with the processes it spawned, and makes the evaluation
+/// return `EvalError::Cancelled`. Clones of a token share its state.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Returns a token that is not cancelled.
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// Cancels the evaluations using this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns whether the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn flag(&self) -> &AtomicBool {
+        &self.0
+    }
+}
+
+/// Handle to an evaluation running in the background.
+///
+/// Returned by `Evaluator::spawn_eval`. Dropping the handle does not cancel
+/// the evaluation.
+///
+/// # Examples
+///
+/// ```rust
+/// use everust::{EvalError, Evaluator};
+/// let handle = Evaluator::new().spawn_eval("loop {}");
+/// handle.cancel();
+/// match handle.join() {
+///     Err(EvalError::Cancelled) => {}
+///     r => panic!("Unexpected result: {:?}", r),
+/// }
+/// ```
+#[derive(Debug)]
+pub struct EvalHandle {
+    token: CancelToken,
+    thread: JoinHandle<Result<EvalOutput, EvalError>>,
+}
+
+impl EvalHandle {
+    pub(crate) fn new(token: CancelToken,
+        thread: JoinHandle<Result<EvalOutput, EvalError>>) -> EvalHandle
+    {
+        EvalHandle {token, thread}
+    }
+
+    /// Cancels the evaluation.
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// Returns the token cancelling the evaluation, e.g. to cancel it from
+    /// another thread.
+    pub fn cancel_token(&self) -> CancelToken {
+        self.token.clone()
+    }
+
+    /// Returns whether the evaluation is over.
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Waits for the evaluation to finish and returns its result.
+    pub fn join(self) -> Result<EvalOutput, EvalError> {
+        self.thread.join().unwrap_or_else(|e| panic::resume_unwind(e))
+    }
+}
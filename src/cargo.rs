@@ -0,0 +1,431 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+use crate::diagnostic::{Diagnostic, Level};
+use crate::evaluator::Edition;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Name of the package generated around the evaluated code.
+const PACKAGE_NAME: &str = "everust-snippet";
+
+/// Configuration of the Cargo backend.
+///
+/// With this backend, the evaluated code is built as the binary of a
+/// throwaway Cargo package, so it can use external crates. The package is
+/// built with `cargo rustc`, and the codegen options and cfgs of the
+/// evaluator only apply to the evaluated code.
+///
+/// Dependencies can also be declared in a header of the evaluated code (see
+/// `everust::eval`) when enabled with `EvaluatorBuilder::header_dependencies`,
+/// in which case the Cargo backend is used even when not configured.
+///
+/// Dependencies come from the registry, local paths or a vendored directory
+/// (as created by `cargo vendor`). Evaluation works without network access
+/// with `offline` when dependencies are vendored, local or already in the
+/// Cargo cache.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use everust::{Cargo, Dependency, Evaluator};
+/// let cargo = Cargo::new()
+///     .dependency(Dependency::new("itertools", "0.10"))
+///     .target_dir("/var/cache/everust/target");
+/// let evaluator = Evaluator::builder().cargo(cargo).build();
+/// let code = "use itertools::Itertools; [1, 2].iter().join(\", \")";
+/// assert_eq!("\"1, 2\"", evaluator.eval(code).unwrap().value);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Cargo {
+    cargo: PathBuf,
+    dependencies: Vec<Dependency>,
+    offline: bool,
+    vendor_dir: Option<PathBuf>,
+    target_dir: Option<PathBuf>,
+}
+
+impl Cargo {
+    /// Returns a configuration using cargo from the PATH, without
+    /// dependencies.
+    pub fn new() -> Cargo {
+        Cargo {
+            cargo: PathBuf::from("cargo"),
+            dependencies: Vec::new(),
+            offline: false,
+            vendor_dir: None,
+            target_dir: None,
+        }
+    }
+
+    /// Sets the path to the cargo binary.
+    ///
+    /// A path without separators is looked up in the PATH.
+    pub fn cargo<P: Into<PathBuf>>(mut self, path: P) -> Cargo {
+        self.cargo = path.into();
+        self
+    }
+
+    /// Adds a dependency.
+    pub fn dependency(mut self, dependency: Dependency) -> Cargo {
+        self.dependencies.push(dependency);
+        self
+    }
+
+    /// Prevents cargo from accessing the network.
+    pub fn offline(mut self, offline: bool) -> Cargo {
+        self.offline = offline;
+        self
+    }
+
+    /// Replaces crates.io with a directory of vendored crates.
+    ///
+    /// A relative path is resolved like that of a path dependency (see
+    /// `Dependency::path`).
+    pub fn vendor_dir<P: Into<PathBuf>>(mut self, dir: P) -> Cargo {
+        self.vendor_dir = Some(dir.into());
+        self
+    }
+
+    /// Sets the target directory shared by evaluations, so that dependencies
+    /// are only built once.
+    ///
+    /// By default, every evaluation builds its dependencies from scratch.
+    pub fn target_dir<P: Into<PathBuf>>(mut self, dir: P) -> Cargo {
+        self.target_dir = Some(dir.into());
+        self
+    }
+
+    /// Returns the path to the cargo binary.
+    pub fn path(&self) -> &Path {
+        &self.cargo
+    }
+
+    /// Returns the dependencies.
+    pub fn dependencies(&self) -> &[Dependency] {
+        &self.dependencies
+    }
+}
+
+impl Default for Cargo {
+    fn default() -> Cargo {
+        Cargo::new()
+    }
+}
+
+/// Dependency of the evaluated code.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Dependency {
+    #[serde(skip)]
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    features: Vec<String>,
+    #[serde(rename = "default-features")]
+    default_features: bool,
+}
+
+impl Dependency {
+    /// Returns a dependency on a crate from the registry with a version
+    /// requirement (e.g. `"1.0"`).
+    pub fn new<N, V>(name: N, version: V) -> Dependency
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        Dependency {
+            name: name.into(),
+            version: Some(version.into()),
+            path: None,
+            features: Vec::new(),
+            default_features: true,
+        }
+    }
+
+    /// Returns a dependency on a crate in a local directory.
+    ///
+    /// A relative path is resolved against the directory set with
+    /// `EvaluatorBuilder::current_dir`, or else the current directory.
+    pub fn path<N, P>(name: N, path: P) -> Dependency
+    where
+        N: Into<String>,
+        P: Into<PathBuf>,
+    {
+        Dependency {
+            name: name.into(),
+            version: None,
+            path: Some(path.into()),
+            features: Vec::new(),
+            default_features: true,
+        }
+    }
+
+    /// Enables a feature of the dependency.
+    pub fn feature<S: Into<String>>(mut self, feature: S) -> Dependency {
+        self.features.push(feature.into());
+        self
+    }
+
+    /// Sets whether the default features of the dependency are enabled.
+    pub fn default_features(mut self, enabled: bool) -> Dependency {
+        self.default_features = enabled;
+        self
+    }
+
+    /// Returns the name of the dependency.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Parses a dependency specification from a manifest.
+    pub(crate) fn from_toml(name: String, spec: toml::Value)
+        -> Result<Dependency, String>
+    {
+        let table = match spec {
+            toml::Value::String(version) => {
+                return Ok(Dependency::new(name, version))
+            }
+            toml::Value::Table(table) => table,
+            _ => return Err(format!("Invalid dependency `{}`", name)),
+        };
+        let mut dependency = Dependency {
+            name,
+            version: None,
+            path: None,
+            features: Vec::new(),
+            default_features: true,
+        };
+        for (key, value) in table {
+            let invalid = || {
+                format!("Invalid `{}` for dependency `{}`", key,
+                    dependency.name)
+            };
+            match &key[..] {
+                "version" => {
+                    let version = value.as_str().ok_or_else(invalid)?;
+                    dependency.version = Some(version.to_owned());
+                }
+                "path" => {
+                    let path = value.as_str().ok_or_else(invalid)?;
+                    dependency.path = Some(PathBuf::from(path));
+                }
+                "features" => {
+                    let features = value.as_array()
+                        .and_then(|features| {
+                            features.iter()
+                                .map(|f| f.as_str().map(str::to_owned))
+                                .collect::<Option<Vec<_>>>()
+                        })
+                        .ok_or_else(invalid)?;
+                    dependency.features = features;
+                }
+                "default-features" => {
+                    let enabled = value.as_bool().ok_or_else(invalid)?;
+                    dependency.default_features = enabled;
+                }
+                _ => {
+                    return Err(format!("Unsupported key `{}` for dependency \
+                        `{}`", key, dependency.name))
+                }
+            }
+        }
+        Ok(dependency)
+    }
+}
+
+#[derive(Serialize)]
+struct Manifest<'a> {
+    package: Package<'a>,
+    bin: Vec<Bin<'a>>,
+    dependencies: BTreeMap<&'a str, Dependency>,
+    workspace: BTreeMap<String, String>,
+}
+
+#[derive(Serialize)]
+struct Package<'a> {
+    name: &'a str,
+    version: &'a str,
+    edition: &'a str,
+    publish: bool,
+}
+
+#[derive(Serialize)]
+struct Bin<'a> {
+    name: &'a str,
+    path: &'a str,
+}
+
+/// Writes the manifest and configuration of the package in `dir`.
+///
+/// `code_path` is the path of the source file of the binary `bin`, relative
+/// to `dir`. Relative paths of dependencies and of the vendor directory are
+/// resolved against `base`, since the package is not where they were given.
+pub fn write_package(dir: &Path, cargo: &Cargo, edition: Option<Edition>,
+    bin: &str, code_path: &str, base: &Path) -> io::Result<()>
+{
+    let manifest = Manifest {
+        package: Package {
+            name: PACKAGE_NAME,
+            version: "0.0.0",
+            edition: edition.unwrap_or(Edition::E2015).as_str(),
+            publish: false,
+        },
+        bin: vec![Bin {name: bin, path: code_path}],
+        dependencies: cargo.dependencies.iter()
+            .map(|d| {
+                let path = d.path.as_ref().map(|path| base.join(path));
+                (&d.name[..], Dependency {path, ..d.clone()})
+            })
+            .collect(),
+        workspace: BTreeMap::new(),
+    };
+    let manifest = toml::to_string(&manifest)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    fs::write(dir.join("Cargo.toml"), manifest)?;
+    if let Some(ref vendor_dir) = cargo.vendor_dir {
+        let config = format!("\
+[source.crates-io]
+replace-with = \"vendored-sources\"
+
+[source.vendored-sources]
+directory = {}
+", toml_string(&base.join(vendor_dir).to_string_lossy()));
+        fs::create_dir_all(dir.join(".cargo"))?;
+        fs::write(dir.join(".cargo").join("config.toml"), config)?;
+    }
+    Ok(())
+}
+
+/// Returns a fingerprint of the configuration and of the local crates it
+/// uses, for the cache key.
+///
+/// Paths are resolved against `base` like in `write_package`. The files of
+/// path dependencies and of the vendor directory are identified by their
+/// path, size and modification time, like Cargo does for path dependencies.
+pub fn fingerprint(cargo: &Cargo, base: &Path) -> io::Result<String> {
+    let resolved = Cargo {
+        dependencies: cargo.dependencies.iter()
+            .map(|d| Dependency {
+                path: d.path.as_ref().map(|path| base.join(path)),
+                ..d.clone()
+            })
+            .collect(),
+        vendor_dir: cargo.vendor_dir.as_ref().map(|dir| base.join(dir)),
+        ..cargo.clone()
+    };
+    let mut fingerprint = format!("{:?}\n", resolved);
+    let dirs = resolved.dependencies.iter()
+        .filter_map(|d| d.path.as_ref())
+        .chain(&resolved.vendor_dir);
+    for dir in dirs {
+        match fingerprint_dir(dir, &mut fingerprint) {
+            // Reported by the build.
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            r => r?,
+        }
+    }
+    Ok(fingerprint)
+}
+
+/// Appends the path, size and modification time of the files in `dir` to
+/// `fingerprint`, skipping build outputs and hidden directories.
+fn fingerprint_dir(dir: &Path, fingerprint: &mut String) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            let name = entry.file_name();
+            if name != "target" && !name.to_string_lossy().starts_with('.') {
+                fingerprint_dir(&path, fingerprint)?;
+            }
+            continue
+        }
+        // Symbolic links are followed, except to directories.
+        let metadata = fs::metadata(&path)?;
+        if metadata.is_file() {
+            writeln!(fingerprint, "{:?} {} {:?}", path, metadata.len(),
+                metadata.modified()?).unwrap();
+        }
+    }
+    Ok(())
+}
+
+fn toml_string(s: &str) -> String {
+    // JSON strings are valid TOML basic strings.
+    serde_json::to_string(s).unwrap_or_default()
+}
+
+/// Returns the command building the binary `bin` of the package in `dir`, or
+/// only type-checking it if `check` is true.
+///
+/// The arguments for rustc are added by the caller.
+pub fn command(dir: &Path, cargo: &Cargo, bin: &str, check: bool)
+    -> Command
+{
+    let mut cmd = Command::new(&cargo.cargo);
+    cmd.current_dir(dir)
+        .args(["rustc", "--quiet", "--message-format=json", "--bin", bin]);
+    if check {
+        cmd.args(["--profile", "check"]);
+    }
+    if cargo.offline {
+        cmd.arg("--offline");
+    }
+    if let Some(ref target_dir) = cargo.target_dir {
+        cmd.arg("--target-dir").arg(target_dir);
+    }
+    cmd.arg("--");
+    cmd
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+enum Event {
+    CompilerMessage {
+        message: Diagnostic,
+    },
+    CompilerArtifact {
+        executable: Option<PathBuf>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// Output of a cargo build.
+pub struct Build {
+    pub diagnostics: Vec<Diagnostic>,
+    pub executable: Option<PathBuf>,
+}
+
+/// Parses the output of a cargo build.
+///
+/// Errors reported by cargo itself (e.g. failure to resolve dependencies) are
+/// converted to a diagnostic.
+pub fn parse(stdout: &str, stderr: &str) -> Build {
+    let mut build = Build {diagnostics: Vec::new(), executable: None};
+    for line in stdout.lines() {
+        match serde_json::from_str(line) {
+            Ok(Event::CompilerMessage {message}) => {
+                build.diagnostics.push(message);
+            }
+            Ok(Event::CompilerArtifact {executable: Some(executable)}) => {
+                build.executable = Some(executable);
+            }
+            Ok(_) | Err(_) => {}
+        }
+    }
+    let has_errors = build.diagnostics.iter()
+        .any(|d| d.level == Level::Error);
+    if !has_errors && !stderr.trim().is_empty() {
+        build.diagnostics.push(Diagnostic::unstructured(stderr.trim_end()
+            .to_owned()));
+    }
+    build
+}
@@ -0,0 +1,302 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+use crate::evaluator::{self, Evaluator, Step};
+use crate::limits;
+use crate::output::{EvalOutput, OutputChunk, Timings};
+use crate::process::Finished;
+use crate::seccomp::{self, Monitor};
+use crate::source::{self, SourceMap};
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::time::{Duration, Instant};
+use tempdir::TempDir;
+use crate::{EvalError, OtherError, Phase};
+
+/// Program built from evaluated code, which can be run several times.
+///
+/// Each run happens in its own temporary directory, so a program can be run
+/// concurrently from several threads. The program is removed when the handle
+/// is dropped.
+///
+/// # Examples
+///
+/// ```rust
+/// use everust::{Evaluator, RunOptions};
+/// let code = "let mut s = String::new(); \
+///     std::io::stdin().read_line(&mut s).unwrap(); \
+///     s.trim().parse::<i32>().unwrap() * 2";
+/// let compiled = Evaluator::new().compile(code).unwrap();
+/// for n in 1..3 {
+///     let options = RunOptions::new().stdin(n.to_string());
+///     let output = compiled.run_with(&options).unwrap();
+///     assert_eq!((n * 2).to_string(), output.value);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Compiled {
+    evaluator: Evaluator,
+    dir: TempDir,
+    map: SourceMap,
+    compile_time: Duration,
+    cached: bool,
+}
+
+impl Compiled {
+    pub(crate) fn new(evaluator: Evaluator, dir: TempDir, map: SourceMap,
+        compile_time: Duration, cached: bool) -> Compiled
+    {
+        Compiled {evaluator, dir, map, compile_time, cached}
+    }
+
+    /// Runs the program with the configuration of the evaluator that built
+    /// it.
+    pub fn run(&self) -> Result<EvalOutput, EvalError> {
+        self.run_with(&RunOptions::new())
+    }
+
+    /// Runs the program with additional options.
+    pub fn run_with(&self, options: &RunOptions)
+        -> Result<EvalOutput, EvalError>
+    {
+        let (run_dir, step, monitor) = self.start_run(options)?;
+        let start = Instant::now();
+        let out = step.run(self.evaluator.cancel())?;
+        self.finish_run(&run_dir, out, start.elapsed(), monitor)
+    }
+
+    /// Runs the program, passing what it writes to stdout and stderr to
+    /// `on_output` as it runs.
+    ///
+    /// The chunks are passed on the calling thread, in the order they were
+    /// read, and contain the output exactly as written by the program. The
+    /// returned output also contains everything the program wrote.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use everust::{Evaluator, RunOptions, Stream};
+    /// let compiled = Evaluator::new().compile(r#"println!("hi"); 1"#)
+    ///     .unwrap();
+    /// let mut stdout = Vec::new();
+    /// let output = compiled.run_streaming(&RunOptions::new(), |chunk| {
+    ///     if chunk.stream == Stream::Stdout {
+    ///         stdout.extend(chunk.data);
+    ///     }
+    /// }).unwrap();
+    /// assert_eq!(b"hi\n", &stdout[..]);
+    /// assert_eq!("1", output.value);
+    /// ```
+    pub fn run_streaming<F>(&self, options: &RunOptions, mut on_output: F)
+        -> Result<EvalOutput, EvalError>
+    where
+        F: FnMut(OutputChunk),
+    {
+        let (run_dir, step, monitor) = self.start_run(options)?;
+        let start = Instant::now();
+        let out = step.run_with_output(self.evaluator.cancel(),
+            Some(&mut on_output))?;
+        self.finish_run(&run_dir, out, start.elapsed(), monitor)
+    }
+
+    /// Async version of `run`. See `Evaluator::eval_async` for details.
+    #[cfg(feature = "tokio")]
+    pub async fn run_async(&self) -> Result<EvalOutput, EvalError> {
+        self.run_with_async(&RunOptions::new()).await
+    }
+
+    /// Async version of `run_with`. See `Evaluator::eval_async` for details.
+    #[cfg(feature = "tokio")]
+    pub async fn run_with_async(&self, options: &RunOptions)
+        -> Result<EvalOutput, EvalError>
+    {
+        let (run_dir, step, monitor) = self.start_run(options)?;
+        let start = Instant::now();
+        let out = step.run_async().await?;
+        self.finish_run(&run_dir, out, start.elapsed(), monitor)
+    }
+
+    /// Copies the program to a new temporary directory and returns the step
+    /// running it there, with the monitor of its forbidden system calls.
+    fn start_run(&self, options: &RunOptions)
+        -> Result<(TempDir, Step, Option<Monitor>), EvalError>
+    {
+        let run_dir = TempDir::new("everust-run")
+            .map_err(OtherError::CreateTempDir)?;
+        fs::copy(self.dir.path().join(evaluator::PROGRAM_NAME),
+            run_dir.path().join(evaluator::PROGRAM_NAME))
+            .map_err(OtherError::CopyProgram)?;
+        let spawn_error = if self.evaluator.sandbox().is_some() {
+            EvalError::SandboxUnavailable
+        } else {
+            evaluator::spawn_prog_error
+        };
+        let options = self.evaluator.run_options().merge(options);
+        let mut cmd = self.evaluator.program_command(run_dir.path(),
+            &options).map_err(spawn_error)?;
+        let monitor = match self.evaluator.seccomp() {
+            Some(profile) => Some(seccomp::apply(&mut cmd, profile)
+                .map_err(OtherError::Seccomp)?),
+            None => None,
+        };
+        let step = Step {
+            phase: Phase::Run,
+            cmd,
+            stdin: options.stdin,
+            timeout: self.evaluator.run_timeout(),
+            spawn_error,
+        };
+        Ok((run_dir, step, monitor))
+    }
+
+    /// Returns the output of a run of the program, or the reason it failed.
+    fn finish_run(&self, run_dir: &TempDir, out: Finished, run_time: Duration,
+        monitor: Option<Monitor>) -> Result<EvalOutput, EvalError>
+    {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        let stderr = self.map.map_output(self.map.strip_replayed(&stderr));
+        if let Some(monitor) = monitor {
+            if let Some(syscall) = seccomp::blocked(monitor.finish(),
+                &out.status)
+            {
+                return Err(EvalError::SyscallBlocked {syscall, stderr})
+            }
+        }
+        if out.status.success() {
+            let read = |name| {
+                match fs::read(run_dir.path().join(name)) {
+                    Ok(s) => Ok(String::from_utf8_lossy(&s).into_owned()),
+                    Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                        Ok(String::new())
+                    }
+                    Err(e) => Err(OtherError::ReadValue(e)),
+                }
+            };
+            return Ok(EvalOutput {
+                value: read(source::VALUE_FILE_NAME)?,
+                type_name: read(source::TYPE_FILE_NAME)?,
+                stdout: self.map.strip_replayed(&String::from_utf8_lossy(
+                    &out.stdout)).to_owned(),
+                stderr,
+                status: out.status,
+                timings: Timings {compile: self.compile_time, run: run_time},
+                cached: self.cached,
+            })
+        }
+        let limits = self.evaluator.resource_limits();
+        match limits::exceeded(limits, &out.status, &stderr) {
+            Some(limit) => {
+                Err(EvalError::ResourceLimitExceeded {limit, stderr})
+            }
+            None => Err(EvalError::ProgReturnedError(stderr)),
+        }
+    }
+
+    /// Returns whether the program was found in the cache instead of being
+    /// built.
+    pub fn cached(&self) -> bool {
+        self.cached
+    }
+}
+
+/// Options for a run of a compiled program.
+///
+/// Options can be set for every run of the programs built by an evaluator
+/// with `EvaluatorBuilder::run_options`, and for a single run with
+/// `Compiled::run_with`.
+///
+/// # Examples
+///
+/// ```rust
+/// use everust::{Evaluator, RunOptions};
+/// let options = RunOptions::new()
+///     .env_clear()
+///     .env("GREETING", "hello")
+///     .arg("world");
+/// let evaluator = Evaluator::builder().run_options(options).build();
+/// let code = r#"format!("{} {}", std::env::var("GREETING").unwrap(),
+///     std::env::args().nth(1).unwrap())"#;
+/// assert_eq!(r#""hello world""#, evaluator.eval(code).unwrap().value);
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RunOptions {
+    stdin: Option<Vec<u8>>,
+    args: Vec<OsString>,
+    envs: Vec<(OsString, OsString)>,
+    env_clear: bool,
+}
+
+impl RunOptions {
+    /// Returns options running the program without input, arguments or
+    /// additional environment variables.
+    pub fn new() -> RunOptions {
+        RunOptions::default()
+    }
+
+    /// Returns the options of a run with these options as defaults.
+    ///
+    /// The input of `options` replaces the default one, and its arguments
+    /// and environment variables come after the default ones.
+    pub(crate) fn merge(&self, options: &RunOptions) -> RunOptions {
+        RunOptions {
+            stdin: options.stdin.clone().or_else(|| self.stdin.clone()),
+            args: self.args.iter().chain(&options.args).cloned().collect(),
+            envs: self.envs.iter().chain(&options.envs).cloned().collect(),
+            env_clear: self.env_clear || options.env_clear,
+        }
+    }
+
+    /// Sets the data the program reads from its standard input.
+    pub fn stdin<B: Into<Vec<u8>>>(mut self, data: B) -> RunOptions {
+        self.stdin = Some(data.into());
+        self
+    }
+
+    /// Adds a command-line argument.
+    pub fn arg<S: AsRef<OsStr>>(mut self, arg: S) -> RunOptions {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    /// Adds command-line arguments.
+    pub fn args<I, S>(mut self, args: I) -> RunOptions
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args.extend(args.into_iter().map(|a| a.as_ref().to_owned()));
+        self
+    }
+
+    /// Sets an environment variable.
+    pub fn env<K, V>(mut self, key: K, value: V) -> RunOptions
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.envs.push((key.as_ref().to_owned(), value.as_ref().to_owned()));
+        self
+    }
+
+    /// Does not let the program inherit the environment of the current
+    /// process.
+    ///
+    /// The program then only gets the variables set with `env` and
+    /// `EvaluatorBuilder::env`.
+    pub fn env_clear(mut self) -> RunOptions {
+        self.env_clear = true;
+        self
+    }
+
+    pub(crate) fn get_args(&self) -> &[OsString] {
+        &self.args
+    }
+
+    pub(crate) fn get_envs(&self) -> &[(OsString, OsString)] {
+        &self.envs
+    }
+
+    pub(crate) fn get_env_clear(&self) -> bool {
+        self.env_clear
+    }
+}
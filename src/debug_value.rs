@@ -0,0 +1,560 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+use crate::diff::Diff;
+use std::error::Error;
+use std::fmt::{Display, self, Write};
+use std::str::FromStr;
+
+/// Maximum nesting depth of parsed values, so that parsing does not overflow
+/// the stack.
+const MAX_DEPTH: usize = 256;
+
+/// Value parsed from the output of `Debug`.
+///
+/// This parses what derived and standard `Debug` implementations print, both
+/// compact (`{:?}`) and pretty (`{:#?}`), so that the value returned by
+/// `eval` can be inspected without changing the evaluated code.
+///
+/// `Display` prints the value like `{:?}` would, and like `{:#?}` with the
+/// alternate flag (`{:#}`).
+///
+/// # Examples
+///
+/// ```rust
+/// use everust::DebugValue;
+/// let value = DebugValue::parse(r#"Foo { a: [1, 2], b: Some("x") }"#)
+///     .unwrap();
+/// assert_eq!(Some(&DebugValue::Int(2)),
+///     value.field("a").and_then(|a| a.get(1)));
+/// assert_eq!("Foo {\n    a: [\n        1,\n        2,\n    ],\n    \
+///     b: Some(\n        \"x\",\n    ),\n}", format!("{:#}", value));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum DebugValue {
+    /// `true` or `false`.
+    Bool(bool),
+    /// Integer, e.g. `-3`.
+    Int(i128),
+    /// Floating point number, e.g. `1.5`, `1e100`, `NaN` or `-inf`.
+    Float(f64),
+    /// Character, e.g. `'a'`.
+    Char(char),
+    /// String, e.g. `"a\n"`, unescaped.
+    Str(String),
+    /// Tuple, e.g. `(1, "a")`. `()` is an empty tuple.
+    Tuple(Vec<DebugValue>),
+    /// List, e.g. `[1, 2]` for a `Vec` or a slice.
+    List(Vec<DebugValue>),
+    /// Set, e.g. `{1, 2}`.
+    Set(Vec<DebugValue>),
+    /// Map, e.g. `{"a": 1}`. `{}` is parsed as an empty map.
+    Map(Vec<(DebugValue, DebugValue)>),
+    /// Struct or enum variant with named fields, e.g. `Point { x: 1, y: 2 }`.
+    Struct {
+        /// Name of the struct or variant.
+        name: String,
+        /// Fields in order.
+        fields: Vec<(String, DebugValue)>,
+    },
+    /// Tuple struct or enum variant with unnamed fields, e.g. `Some(1)`.
+    TupleStruct {
+        /// Name of the struct or variant.
+        name: String,
+        /// Fields in order.
+        fields: Vec<DebugValue>,
+    },
+    /// Unit struct or enum variant, e.g. `None`.
+    Unit(String),
+    /// Text that does not follow the conventions above, e.g. `1.5s` for a
+    /// `Duration` or an integer too large for `i128`.
+    Raw(String),
+}
+
+impl DebugValue {
+    /// Parses the output of `Debug`.
+    ///
+    /// Values nested more than 256 levels deep are rejected.
+    pub fn parse(s: &str) -> Result<DebugValue, DebugParseError> {
+        let mut parser = Parser {s, pos: 0, depth: 0};
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos < s.len() {
+            return Err(parser.error("Unexpected trailing characters"))
+        }
+        Ok(value)
+    }
+
+    /// Returns the name of a struct, tuple struct or unit value.
+    pub fn name(&self) -> Option<&str> {
+        match *self {
+            DebugValue::Struct {ref name, ..}
+            | DebugValue::TupleStruct {ref name, ..}
+            | DebugValue::Unit(ref name) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Returns the field of a struct with the given name.
+    pub fn field(&self, name: &str) -> Option<&DebugValue> {
+        match *self {
+            DebugValue::Struct {ref fields, ..} => {
+                fields.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the element at the given index of a tuple, list, set or tuple
+    /// struct.
+    pub fn get(&self, index: usize) -> Option<&DebugValue> {
+        self.elements().and_then(|elements| elements.get(index))
+    }
+
+    /// Returns the elements of a tuple, list, set or tuple struct.
+    pub fn elements(&self) -> Option<&[DebugValue]> {
+        match *self {
+            DebugValue::Tuple(ref elements)
+            | DebugValue::List(ref elements)
+            | DebugValue::Set(ref elements)
+            | DebugValue::TupleStruct {fields: ref elements, ..} => {
+                Some(elements)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the structural difference from this value to `new`.
+    pub fn diff(&self, new: &DebugValue) -> Diff {
+        Diff::new(self, new)
+    }
+
+    fn write(&self, out: &mut String, pretty: bool, indent: usize) {
+        match *self {
+            DebugValue::Bool(b) => write!(out, "{:?}", b).unwrap(),
+            DebugValue::Int(n) => write!(out, "{:?}", n).unwrap(),
+            DebugValue::Float(x) => write!(out, "{:?}", x).unwrap(),
+            DebugValue::Char(c) => write!(out, "{:?}", c).unwrap(),
+            DebugValue::Str(ref s) => write!(out, "{:?}", s).unwrap(),
+            DebugValue::Tuple(ref elements) => {
+                // A tuple of one element has a trailing comma.
+                let last = if elements.len() == 1 { "," } else { "" };
+                write_seq(out, pretty, indent, "(", ")", last, elements.iter()
+                    .map(|e| (None, e)));
+            }
+            DebugValue::List(ref elements) => {
+                write_seq(out, pretty, indent, "[", "]", "", elements.iter()
+                    .map(|e| (None, e)));
+            }
+            DebugValue::Set(ref elements) => {
+                write_seq(out, pretty, indent, "{", "}", "", elements.iter()
+                    .map(|e| (None, e)));
+            }
+            DebugValue::Map(ref entries) => {
+                write_seq(out, pretty, indent, "{", "}", "", entries.iter()
+                    .map(|(k, v)| (Some(Key::Value(k)), v)));
+            }
+            DebugValue::Struct {ref name, ref fields} => {
+                out.push_str(name);
+                if !fields.is_empty() {
+                    write_seq(out, pretty, indent, " { ", " }", "",
+                        fields.iter().map(|(k, v)| (Some(Key::Name(k)), v)));
+                }
+            }
+            DebugValue::TupleStruct {ref name, ref fields} => {
+                out.push_str(name);
+                write_seq(out, pretty, indent, "(", ")", "", fields.iter()
+                    .map(|e| (None, e)));
+            }
+            DebugValue::Unit(ref name) => out.push_str(name),
+            DebugValue::Raw(ref s) => out.push_str(s),
+        }
+    }
+}
+
+enum Key<'a> {
+    Name(&'a str),
+    Value(&'a DebugValue),
+}
+
+/// Writes a delimited sequence of values with optional keys.
+///
+/// In compact form, `last` is written after the last element.
+fn write_seq<'a, I>(out: &mut String, pretty: bool, indent: usize,
+    open: &str, close: &str, last: &str, entries: I)
+where
+    I: Iterator<Item = (Option<Key<'a>>, &'a DebugValue)>,
+{
+    let mut entries = entries.peekable();
+    if entries.peek().is_none() {
+        out.push_str(open.trim());
+        out.push_str(close.trim());
+        return
+    }
+    if pretty {
+        out.push_str(open.trim_end());
+        out.push('\n');
+    } else {
+        out.push_str(open);
+    }
+    let mut first = true;
+    for (key, value) in entries {
+        if pretty {
+            push_indent(out, indent + 1);
+        } else if !first {
+            out.push_str(", ");
+        }
+        first = false;
+        match key {
+            Some(Key::Name(name)) => {
+                out.push_str(name);
+                out.push_str(": ");
+            }
+            Some(Key::Value(key)) => {
+                key.write(out, pretty, indent + 1);
+                out.push_str(": ");
+            }
+            None => {}
+        }
+        value.write(out, pretty, indent + 1);
+        if pretty {
+            out.push_str(",\n");
+        }
+    }
+    if pretty {
+        push_indent(out, indent);
+        out.push_str(close.trim_start());
+    } else {
+        out.push_str(last);
+        out.push_str(close);
+    }
+}
+
+fn push_indent(out: &mut String, indent: usize) {
+    for _ in 0..indent {
+        out.push_str("    ");
+    }
+}
+
+impl Display for DebugValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut out = String::new();
+        self.write(&mut out, f.alternate(), 0);
+        f.write_str(&out)
+    }
+}
+
+impl FromStr for DebugValue {
+    type Err = DebugParseError;
+
+    fn from_str(s: &str) -> Result<DebugValue, DebugParseError> {
+        DebugValue::parse(s)
+    }
+}
+
+/// Error returned when `Debug` output cannot be parsed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DebugParseError {
+    offset: usize,
+    message: String,
+}
+
+impl DebugParseError {
+    /// Returns the byte offset in the input where the error was detected.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl Error for DebugParseError {}
+
+impl Display for DebugParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> DebugParseError {
+        DebugParseError {offset: self.pos, message: message.to_owned()}
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.s[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consumes `token` after optional whitespace if it is next.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), DebugParseError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected `{}`", token)))
+        }
+    }
+
+    fn value(&mut self) -> Result<DebugValue, DebugParseError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("Value is nested too deeply"))
+        }
+        self.depth += 1;
+        let value = self.value_body();
+        self.depth -= 1;
+        value
+    }
+
+    fn value_body(&mut self) -> Result<DebugValue, DebugParseError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('"') => self.string().map(DebugValue::Str),
+            Some('\'') => self.char(),
+            Some('(') => {
+                self.pos += 1;
+                self.values(")").map(DebugValue::Tuple)
+            }
+            Some('[') => {
+                self.pos += 1;
+                self.values("]").map(DebugValue::List)
+            }
+            Some('{') => self.set_or_map(),
+            Some(c) if c == '_' || c.is_alphabetic() => self.named(),
+            Some(_) => self.atom(),
+            None => Err(self.error("Expected a value")),
+        }
+    }
+
+    /// Parses values separated by commas up to `close`, allowing a trailing
+    /// comma.
+    fn values(&mut self, close: &str)
+        -> Result<Vec<DebugValue>, DebugParseError>
+    {
+        let mut values = Vec::new();
+        while !self.eat(close) {
+            values.push(self.value()?);
+            if !self.eat(",") {
+                self.expect(close)?;
+                break
+            }
+        }
+        Ok(values)
+    }
+
+    fn set_or_map(&mut self) -> Result<DebugValue, DebugParseError> {
+        self.pos += 1;
+        if self.eat("}") {
+            return Ok(DebugValue::Map(Vec::new()))
+        }
+        let first = self.value()?;
+        if !self.eat(":") {
+            let mut elements = vec![first];
+            if self.eat(",") {
+                elements.extend(self.values("}")?);
+            } else {
+                self.expect("}")?;
+            }
+            return Ok(DebugValue::Set(elements))
+        }
+        let mut entries = vec![(first, self.value()?)];
+        while self.eat(",") {
+            if self.eat("}") {
+                return Ok(DebugValue::Map(entries))
+            }
+            let key = self.value()?;
+            self.expect(":")?;
+            entries.push((key, self.value()?));
+        }
+        self.expect("}")?;
+        Ok(DebugValue::Map(entries))
+    }
+
+    /// Parses a value starting with an identifier.
+    fn named(&mut self) -> Result<DebugValue, DebugParseError> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c == '_' || c.is_alphanumeric() {
+                self.pos += c.len_utf8();
+            } else if self.rest().starts_with("::") {
+                self.pos += 2;
+            } else {
+                break
+            }
+        }
+        // Generic arguments, as in `PhantomData<i32>`.
+        if self.peek() == Some('<') {
+            let mut depth = 0;
+            loop {
+                if self.eat("->") {
+                    continue
+                }
+                match self.next() {
+                    Some('<') => depth += 1,
+                    Some('>') => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break
+                        }
+                    }
+                    Some(_) => {}
+                    None => return Err(self.error("Unclosed `<`")),
+                }
+            }
+        }
+        let name = &self.s[start..self.pos];
+        match name {
+            "true" => return Ok(DebugValue::Bool(true)),
+            "false" => return Ok(DebugValue::Bool(false)),
+            "NaN" => return Ok(DebugValue::Float(f64::NAN)),
+            "inf" => return Ok(DebugValue::Float(f64::INFINITY)),
+            _ => {}
+        }
+        let name = name.to_owned();
+        let after_name = self.pos;
+        if self.eat("(") {
+            let fields = self.values(")")?;
+            return Ok(DebugValue::TupleStruct {name, fields})
+        }
+        if self.eat("{") {
+            let mut fields = Vec::new();
+            while !self.eat("}") {
+                if self.eat("..") {
+                    self.expect("}")?;
+                    break
+                }
+                self.skip_whitespace();
+                let start = self.pos;
+                while let Some(c) = self.peek() {
+                    if c == '_' || c == '#' || c.is_alphanumeric() {
+                        self.pos += c.len_utf8();
+                    } else {
+                        break
+                    }
+                }
+                if start == self.pos {
+                    return Err(self.error("Expected a field name"))
+                }
+                let field = self.s[start..self.pos].to_owned();
+                self.expect(":")?;
+                fields.push((field, self.value()?));
+                if !self.eat(",") {
+                    self.expect("}")?;
+                    break
+                }
+            }
+            return Ok(DebugValue::Struct {name, fields})
+        }
+        self.pos = after_name;
+        Ok(DebugValue::Unit(name))
+    }
+
+    /// Parses a number or another token up to the next delimiter.
+    fn atom(&mut self) -> Result<DebugValue, DebugParseError> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || ",:()[]{}".contains(c) {
+                break
+            }
+            self.pos += c.len_utf8();
+        }
+        let token = &self.s[start..self.pos];
+        if token.is_empty() {
+            return Err(self.error("Expected a value"))
+        }
+        if let Ok(n) = token.parse::<i128>() {
+            return Ok(DebugValue::Int(n))
+        }
+        let is_float = token.contains(['.', 'e', 'E']) || token == "-inf";
+        match token.parse::<f64>() {
+            Ok(x) if is_float => Ok(DebugValue::Float(x)),
+            _ => Ok(DebugValue::Raw(token.to_owned())),
+        }
+    }
+
+    fn char(&mut self) -> Result<DebugValue, DebugParseError> {
+        let start = self.pos;
+        self.pos += 1;
+        let c = match self.next() {
+            Some('\\') => self.escape()?,
+            Some(c) => c,
+            None => return Err(self.error("Unterminated character")),
+        };
+        if self.next() != Some('\'') {
+            self.pos = start;
+            return Err(self.error("Invalid character"))
+        }
+        Ok(DebugValue::Char(c))
+    }
+
+    fn string(&mut self) -> Result<String, DebugParseError> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => s.push(self.escape()?),
+                Some(c) => s.push(c),
+                None => {
+                    self.pos = start;
+                    return Err(self.error("Unterminated string"))
+                }
+            }
+        }
+    }
+
+    /// Parses an escape sequence after the backslash.
+    fn escape(&mut self) -> Result<char, DebugParseError> {
+        let c = match self.next() {
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('0') => '\0',
+            Some(c @ ('\\' | '\'' | '"')) => c,
+            Some('u') => {
+                self.expect("{")?;
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+                    self.pos += 1;
+                }
+                let code = u32::from_str_radix(&self.s[start..self.pos], 16)
+                    .ok()
+                    .and_then(char::from_u32);
+                self.expect("}")?;
+                match code {
+                    Some(c) => c,
+                    None => return Err(self.error("Invalid unicode escape")),
+                }
+            }
+            _ => return Err(self.error("Invalid escape sequence")),
+        };
+        Ok(c)
+    }
+}
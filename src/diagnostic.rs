@@ -0,0 +1,184 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+use std::fmt::{Display, self};
+
+/// Diagnostic emitted by the compiler.
+///
+/// This mirrors the JSON diagnostics emitted by rustc with
+/// `--error-format=json`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Diagnostic {
+    /// Main message.
+    pub message: String,
+    /// Diagnostic code (e.g. `E0308`).
+    pub code: Option<DiagnosticCode>,
+    /// Severity of the diagnostic.
+    pub level: Level,
+    /// Locations in the source code the diagnostic refers to.
+    pub spans: Vec<DiagnosticSpan>,
+    /// Attached notes and help messages.
+    pub children: Vec<Diagnostic>,
+    /// Human-readable message as it would be printed by rustc.
+    pub rendered: Option<String>,
+}
+
+impl Diagnostic {
+    /// Returns the primary spans of this diagnostic.
+    pub fn primary_spans(&self) -> impl Iterator<Item = &DiagnosticSpan> {
+        self.spans.iter().filter(|span| span.is_primary)
+    }
+
+    /// Returns the spans of this diagnostic and its children that come with a
+    /// suggested replacement.
+    pub fn suggestions(&self) -> Vec<&DiagnosticSpan> {
+        let mut suggestions = self.spans.iter()
+            .filter(|span| span.suggested_replacement.is_some())
+            .collect::<Vec<_>>();
+        for child in &self.children {
+            suggestions.extend(child.suggestions());
+        }
+        suggestions
+    }
+
+    pub(crate) fn unstructured(message: String) -> Diagnostic {
+        Diagnostic {
+            rendered: Some(format!("{}\n", message)),
+            message,
+            code: None,
+            level: Level::Error,
+            spans: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.rendered {
+            Some(ref rendered) => f.write_str(rendered),
+            None => writeln!(f, "{}: {}", self.level, self.message),
+        }
+    }
+}
+
+/// Diagnostic code with its explanation.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct DiagnosticCode {
+    /// Code (e.g. `E0308`).
+    pub code: String,
+    /// Explanation of the code, as given by `rustc --explain`.
+    pub explanation: Option<String>,
+}
+
+/// Severity of a diagnostic.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+pub enum Level {
+    /// Error
+    #[serde(rename = "error")]
+    Error,
+    /// Internal compiler error
+    #[serde(rename = "error: internal compiler error")]
+    InternalError,
+    /// Warning
+    #[serde(rename = "warning")]
+    Warning,
+    /// Note
+    #[serde(rename = "note")]
+    Note,
+    /// Help
+    #[serde(rename = "help")]
+    Help,
+    /// Note attached to a failure
+    #[serde(rename = "failure-note")]
+    FailureNote,
+    /// Any other level
+    #[serde(other)]
+    Other,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Level::Error => "error",
+            Level::InternalError => "error: internal compiler error",
+            Level::Warning => "warning",
+            Level::Note => "note",
+            Level::Help => "help",
+            Level::FailureNote => "failure-note",
+            Level::Other => "other",
+        })
+    }
+}
+
+/// Location in the source code a diagnostic refers to.
+///
+/// Lines and columns start at 1. Columns count characters. The end column is
+/// exclusive.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct DiagnosticSpan {
+    /// Name of the source file.
+    ///
+    /// This is `SNIPPET_FILE_NAME` for spans in the evaluated code, in which
+    /// case lines and byte offsets are relative to the evaluated code.
+    pub file_name: String,
+    /// Offset of the first byte of the span in the file.
+    pub byte_start: u32,
+    /// Offset of the byte following the span in the file.
+    pub byte_end: u32,
+    /// First line of the span.
+    pub line_start: usize,
+    /// Last line of the span.
+    pub line_end: usize,
+    /// First column of the span.
+    pub column_start: usize,
+    /// Column following the span.
+    pub column_end: usize,
+    /// Indicates if this is the main location the diagnostic refers to.
+    pub is_primary: bool,
+    /// Source lines covered by the span.
+    pub text: Vec<DiagnosticSpanLine>,
+    /// Label attached to the span.
+    pub label: Option<String>,
+    /// Code suggested to replace the span.
+    pub suggested_replacement: Option<String>,
+    /// Confidence in the suggested replacement.
+    pub suggestion_applicability: Option<Applicability>,
+}
+
+/// Source line covered by a span.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct DiagnosticSpanLine {
+    /// Source code of the line.
+    pub text: String,
+    /// Column where the span starts on this line.
+    pub highlight_start: usize,
+    /// Column where the span ends on this line.
+    pub highlight_end: usize,
+}
+
+/// Confidence in a suggestion.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+pub enum Applicability {
+    /// The suggestion is definitely what the user intended.
+    MachineApplicable,
+    /// The suggestion may be what the user intended.
+    MaybeIncorrect,
+    /// The suggestion contains placeholders.
+    HasPlaceholders,
+    /// The applicability is unknown.
+    Unspecified,
+}
+
+/// Parses the JSON diagnostics written by rustc to stderr.
+///
+/// Lines that are not JSON diagnostics are returned as error diagnostics
+/// without span.
+pub fn parse(stderr: &str) -> Vec<Diagnostic> {
+    stderr.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line)
+                .unwrap_or_else(|_| Diagnostic::unstructured(line.to_owned()))
+        })
+        .collect()
+}
@@ -0,0 +1,385 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+use crate::debug_value::DebugValue;
+use std::fmt::{Display, self};
+
+/// Maximum product of the lengths of two lists aligned element by element.
+/// Longer lists are compared position by position.
+const MAX_ALIGNMENT_COST: usize = 1 << 20;
+
+/// Structural difference between two values.
+///
+/// Returned by `DebugValue::diff`. `Display` renders both values as a tree,
+/// like `{:#?}` would, with lines only in the old value prefixed by `-` and
+/// lines only in the new value prefixed by `+`. Elements of lists are aligned
+/// so that an insertion does not show every following element as changed.
+///
+/// # Examples
+///
+/// ```rust
+/// use everust::DebugValue;
+/// let old = DebugValue::parse("Foo { a: 1, b: true }").unwrap();
+/// let new = DebugValue::parse("Foo { a: 2, b: true }").unwrap();
+/// let diff = old.diff(&new);
+/// assert_eq!(".a", diff.changes()[0].path);
+/// assert_eq!(vec!["  Foo {", "-     a: 1,", "+     a: 2,", "      b: true,",
+///     "  }"], diff.to_string().lines().collect::<Vec<_>>());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diff {
+    root: Node,
+}
+
+impl Diff {
+    pub(crate) fn new(old: &DebugValue, new: &DebugValue) -> Diff {
+        Diff {root: diff(old, new)}
+    }
+
+    /// Returns whether both values are equal.
+    pub fn is_empty(&self) -> bool {
+        matches!(self.root, Node::Equal(_))
+    }
+
+    /// Returns the changes, in the order they appear in the values.
+    pub fn changes(&self) -> Vec<Change> {
+        let mut changes = Vec::new();
+        collect(&self.root, String::new(), &mut changes);
+        changes
+    }
+}
+
+impl Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut out = String::new();
+        render(&self.root, "", 0, "", &mut out);
+        f.write_str(&out)
+    }
+}
+
+/// Change between two values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    /// Location of the change, e.g. `.points[1].x` for a field of an element
+    /// of a list, or `["key"]` for a map entry. It is empty for the root.
+    /// Elements added to or removed from a set are located at the set.
+    ///
+    /// Elements of lists are located by their index in the new list, except
+    /// removed elements, which are located by their index in the old list.
+    pub path: String,
+    /// What changed.
+    pub kind: ChangeKind,
+}
+
+/// Kind of change between two values.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChangeKind {
+    /// The value is only in the new value.
+    Added(DebugValue),
+    /// The value is only in the old value.
+    Removed(DebugValue),
+    /// The value was replaced.
+    Changed {
+        /// Old value.
+        old: DebugValue,
+        /// New value.
+        new: DebugValue,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Equal(DebugValue),
+    Added(DebugValue),
+    Removed(DebugValue),
+    Changed(DebugValue, DebugValue),
+    Nested {
+        shape: Shape,
+        entries: Vec<(Key, Node)>,
+    },
+}
+
+/// Kind of container with entries compared separately.
+#[derive(Clone, Debug, PartialEq)]
+enum Shape {
+    Tuple,
+    List,
+    Set,
+    Map,
+    Struct(String),
+    TupleStruct(String),
+}
+
+impl Shape {
+    fn delimiters(&self) -> (String, &'static str) {
+        match *self {
+            Shape::Tuple => ("(".to_owned(), ")"),
+            Shape::List => ("[".to_owned(), "]"),
+            Shape::Set | Shape::Map => ("{".to_owned(), "}"),
+            Shape::Struct(ref name) => (format!("{} {{", name), "}"),
+            Shape::TupleStruct(ref name) => (format!("{}(", name), ")"),
+        }
+    }
+}
+
+/// Location of an entry in a container.
+#[derive(Clone, Debug, PartialEq)]
+enum Key {
+    Index(usize),
+    Field(String),
+    Entry(DebugValue),
+    SetElement,
+}
+
+impl Key {
+    /// Returns the text written before the entry when rendering.
+    fn label(&self) -> String {
+        match *self {
+            Key::Field(ref name) => format!("{}: ", name),
+            Key::Entry(ref key) => format!("{}: ", key),
+            Key::Index(_) | Key::SetElement => String::new(),
+        }
+    }
+
+    /// Appends the key to the path of its container.
+    fn path(&self, parent: &str) -> String {
+        match *self {
+            Key::Index(i) => format!("{}[{}]", parent, i),
+            Key::Field(ref name) => format!("{}.{}", parent, name),
+            Key::Entry(ref key) => format!("{}[{}]", parent, key),
+            Key::SetElement => parent.to_owned(),
+        }
+    }
+}
+
+fn diff(old: &DebugValue, new: &DebugValue) -> Node {
+    if old == new {
+        return Node::Equal(new.clone())
+    }
+    let (shape, entries) = match (old, new) {
+        (DebugValue::Struct {name: ref old_name, fields: ref old_fields},
+            DebugValue::Struct {name: ref new_name, fields: ref new_fields})
+            if old_name == new_name =>
+        {
+            let shape = Shape::Struct(new_name.clone());
+            (shape, diff_fields(old_fields, new_fields))
+        }
+        (DebugValue::TupleStruct {name: ref old_name, fields: ref old_fields},
+            DebugValue::TupleStruct {name: ref new_name,
+                fields: ref new_fields})
+            if old_name == new_name && old_fields.len() == new_fields.len() =>
+        {
+            let shape = Shape::TupleStruct(new_name.clone());
+            (shape, diff_positions(old_fields, new_fields))
+        }
+        (DebugValue::Tuple(ref old), DebugValue::Tuple(ref new))
+            if old.len() == new.len() =>
+        {
+            (Shape::Tuple, diff_positions(old, new))
+        }
+        (DebugValue::List(ref old), DebugValue::List(ref new)) => {
+            (Shape::List, diff_lists(old, new))
+        }
+        (DebugValue::Set(ref old), DebugValue::Set(ref new)) => {
+            (Shape::Set, diff_sets(old, new))
+        }
+        (DebugValue::Map(ref old), DebugValue::Map(ref new)) => {
+            (Shape::Map, diff_maps(old, new))
+        }
+        _ => return Node::Changed(old.clone(), new.clone()),
+    };
+    Node::Nested {shape, entries}
+}
+
+fn diff_fields(old: &[(String, DebugValue)], new: &[(String, DebugValue)])
+    -> Vec<(Key, Node)>
+{
+    let mut entries = old.iter()
+        .map(|(name, old_value)| {
+            let node = match new.iter().find(|(n, _)| n == name) {
+                Some((_, new_value)) => diff(old_value, new_value),
+                None => Node::Removed(old_value.clone()),
+            };
+            (Key::Field(name.clone()), node)
+        })
+        .collect::<Vec<_>>();
+    entries.extend(new.iter()
+        .filter(|(name, _)| !old.iter().any(|(n, _)| n == name))
+        .map(|(name, value)| {
+            (Key::Field(name.clone()), Node::Added(value.clone()))
+        }));
+    entries
+}
+
+fn diff_positions(old: &[DebugValue], new: &[DebugValue])
+    -> Vec<(Key, Node)>
+{
+    old.iter().zip(new).enumerate()
+        .map(|(i, (old, new))| (Key::Index(i), diff(old, new)))
+        .collect()
+}
+
+/// Compares lists, aligning equal elements with a longest common
+/// subsequence. Elements removed and added at the same place are compared
+/// with each other.
+fn diff_lists(old: &[DebugValue], new: &[DebugValue]) -> Vec<(Key, Node)> {
+    let matches = if old.len().saturating_mul(new.len()) <= MAX_ALIGNMENT_COST
+    {
+        common_subsequence(old, new)
+    } else {
+        Vec::new()
+    };
+    let mut entries = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for (mi, mj) in matches.into_iter().chain(Some((old.len(), new.len()))) {
+        let (removed, added) = (&old[i..mi], &new[j..mj]);
+        let paired = removed.len().min(added.len());
+        for k in 0..paired {
+            entries.push((Key::Index(j + k), diff(&removed[k], &added[k])));
+        }
+        for (k, value) in removed.iter().enumerate().skip(paired) {
+            entries.push((Key::Index(i + k), Node::Removed(value.clone())));
+        }
+        for (k, value) in added.iter().enumerate().skip(paired) {
+            entries.push((Key::Index(j + k), Node::Added(value.clone())));
+        }
+        if mi < old.len() {
+            entries.push((Key::Index(mj), Node::Equal(new[mj].clone())));
+        }
+        i = mi + 1;
+        j = mj + 1;
+    }
+    entries
+}
+
+/// Returns the indices of the elements of a longest common subsequence.
+fn common_subsequence(old: &[DebugValue], new: &[DebugValue])
+    -> Vec<(usize, usize)>
+{
+    let width = new.len() + 1;
+    let mut lengths = vec![0usize; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * width + j] = if old[i] == new[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+    let mut matches = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            matches.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    matches
+}
+
+fn diff_sets(old: &[DebugValue], new: &[DebugValue]) -> Vec<(Key, Node)> {
+    let mut entries = old.iter()
+        .map(|value| {
+            let node = if new.contains(value) {
+                Node::Equal(value.clone())
+            } else {
+                Node::Removed(value.clone())
+            };
+            (Key::SetElement, node)
+        })
+        .collect::<Vec<_>>();
+    entries.extend(new.iter()
+        .filter(|value| !old.contains(value))
+        .map(|value| (Key::SetElement, Node::Added(value.clone()))));
+    entries
+}
+
+fn diff_maps(old: &[(DebugValue, DebugValue)],
+    new: &[(DebugValue, DebugValue)]) -> Vec<(Key, Node)>
+{
+    let mut entries = old.iter()
+        .map(|(key, old_value)| {
+            let node = match new.iter().find(|(k, _)| k == key) {
+                Some((_, new_value)) => diff(old_value, new_value),
+                None => Node::Removed(old_value.clone()),
+            };
+            (Key::Entry(key.clone()), node)
+        })
+        .collect::<Vec<_>>();
+    entries.extend(new.iter()
+        .filter(|(key, _)| !old.iter().any(|(k, _)| k == key))
+        .map(|(key, value)| {
+            (Key::Entry(key.clone()), Node::Added(value.clone()))
+        }));
+    entries
+}
+
+fn collect(node: &Node, path: String, changes: &mut Vec<Change>) {
+    let kind = match *node {
+        Node::Equal(_) => return,
+        Node::Added(ref value) => ChangeKind::Added(value.clone()),
+        Node::Removed(ref value) => ChangeKind::Removed(value.clone()),
+        Node::Changed(ref old, ref new) => {
+            ChangeKind::Changed {old: old.clone(), new: new.clone()}
+        }
+        Node::Nested {ref entries, ..} => {
+            for (key, node) in entries {
+                collect(node, key.path(&path), changes);
+            }
+            return
+        }
+    };
+    changes.push(Change {path, kind});
+}
+
+/// Renders a node at the given depth. `label` is written before the node and
+/// `end` after it.
+fn render(node: &Node, label: &str, depth: usize, end: &str, out: &mut String) {
+    match *node {
+        Node::Equal(ref value) => {
+            render_value(' ', value, label, depth, end, out)
+        }
+        Node::Added(ref value) => {
+            render_value('+', value, label, depth, end, out)
+        }
+        Node::Removed(ref value) => {
+            render_value('-', value, label, depth, end, out)
+        }
+        Node::Changed(ref old, ref new) => {
+            render_value('-', old, label, depth, end, out);
+            render_value('+', new, label, depth, end, out);
+        }
+        Node::Nested {ref shape, ref entries} => {
+            let (open, close) = shape.delimiters();
+            render_line(' ', depth, &format!("{}{}", label, open), out);
+            for (key, node) in entries {
+                render(node, &key.label(), depth + 1, ",", out);
+            }
+            render_line(' ', depth, &format!("{}{}", close, end), out);
+        }
+    }
+}
+
+fn render_value(marker: char, value: &DebugValue, label: &str, depth: usize,
+    end: &str, out: &mut String)
+{
+    let text = format!("{}{:#}{}", label, value, end);
+    for line in text.lines() {
+        render_line(marker, depth, line, out);
+    }
+}
+
+fn render_line(marker: char, depth: usize, line: &str, out: &mut String) {
+    out.push(marker);
+    out.push(' ');
+    for _ in 0..depth {
+        out.push_str("    ");
+    }
+    out.push_str(line);
+    out.push('\n');
+}
@@ -0,0 +1,963 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+use std::env;
+use std::ffi::OsString;
+use std::fmt::{Display, self};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::bindings::Bindings;
+use crate::cache::{self, Cache};
+use crate::cancel::{CancelToken, EvalHandle};
+use crate::cargo::{self, Cargo};
+use crate::diagnostic::{self, Diagnostic, Level};
+use crate::format::OutputFormat;
+use crate::header::{self, Header};
+use crate::compiled::{Compiled, RunOptions};
+use crate::limits::{self, ResourceLimits};
+use crate::output::{EvalOutput, OutputChunk};
+use crate::process::{self, Finished, Outcome, RunError};
+use crate::sandbox::{self, Sandbox};
+use crate::seccomp::SeccompProfile;
+use crate::source::{self, Source, Template, ValueFormat};
+use serde::de::DeserializeOwned;
+use tempdir::TempDir;
+use crate::{EvalError, OtherError, Phase};
+
+/// Name of the program built from the evaluated code.
+pub(crate) const PROGRAM_NAME: &str = "main";
+
+/// Rust edition used to build the evaluated code.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Edition {
+    /// Rust 2015
+    E2015,
+    /// Rust 2018
+    E2018,
+    /// Rust 2021
+    E2021,
+    /// Rust 2024
+    E2024,
+}
+
+impl Edition {
+    /// Returns the edition as expected by rustc's `--edition` flag.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Edition::E2015 => "2015",
+            Edition::E2018 => "2018",
+            Edition::E2021 => "2021",
+            Edition::E2024 => "2024",
+        }
+    }
+}
+
+impl Display for Edition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Evaluates rust code with a given configuration.
+///
+/// An `Evaluator` is created with `Evaluator::new` for the default
+/// configuration, or with `Evaluator::builder` to customize how the code is
+/// built and run.
+///
+/// # Examples
+///
+/// ```rust
+/// use everust::{Edition, Evaluator};
+/// let evaluator = Evaluator::builder()
+///     .edition(Edition::E2021)
+///     .codegen_option("opt-level=2")
+///     .build();
+/// assert_eq!("3", evaluator.eval("1 + 2").unwrap().value);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Evaluator {
+    rustc: PathBuf,
+    edition: Option<Edition>,
+    codegen_options: Vec<String>,
+    cfgs: Vec<String>,
+    envs: Vec<(OsString, OsString)>,
+    current_dir: Option<PathBuf>,
+    compile_timeout: Option<Duration>,
+    run_timeout: Option<Duration>,
+    limits: ResourceLimits,
+    sandbox: Option<Sandbox>,
+    seccomp: Option<SeccompProfile>,
+    cargo: Option<Cargo>,
+    header_dependencies: bool,
+    cache: Option<Cache>,
+    run_options: RunOptions,
+    output_format: OutputFormat,
+    cancel: Option<CancelToken>,
+}
+
+impl Evaluator {
+    /// Returns an evaluator with the default configuration.
+    ///
+    /// The default configuration uses rustc from the PATH with its default
+    /// edition and no additional flags.
+    pub fn new() -> Evaluator {
+        Evaluator {
+            rustc: PathBuf::from("rustc"),
+            edition: None,
+            codegen_options: Vec::new(),
+            cfgs: Vec::new(),
+            envs: Vec::new(),
+            current_dir: None,
+            compile_timeout: None,
+            run_timeout: None,
+            limits: ResourceLimits::new(),
+            sandbox: None,
+            seccomp: None,
+            cargo: None,
+            header_dependencies: false,
+            cache: None,
+            run_options: RunOptions::new(),
+            output_format: OutputFormat::Debug,
+            cancel: None,
+        }
+    }
+
+    /// Returns a builder to configure an evaluator.
+    pub fn builder() -> EvaluatorBuilder {
+        EvaluatorBuilder::new()
+    }
+
+    /// Evaluates rust code.
+    ///
+    /// See `everust::eval` for a description of how the code is evaluated.
+    ///
+    /// The value of the expression is written by the program to a file in
+    /// the evaluation directory, so it is kept apart from the program's own
+    /// output. The file counts towards `ResourceLimits::file_size`.
+    pub fn eval(&self, code: &str) -> Result<EvalOutput, EvalError> {
+        self.compile(code)?.run()
+    }
+
+    /// Builds rust code without running it.
+    ///
+    /// The returned program can be run several times.
+    pub fn compile(&self, code: &str) -> Result<Compiled, EvalError> {
+        self.compile_with_bindings(code, &Bindings::new())
+    }
+
+    /// Evaluates rust code with variables bound to the given values.
+    ///
+    /// See `Bindings`.
+    pub fn eval_with_bindings(&self, code: &str, bindings: &Bindings)
+        -> Result<EvalOutput, EvalError>
+    {
+        self.compile_with_bindings(code, bindings)?.run()
+    }
+
+    /// Builds rust code with variables bound to the given values, without
+    /// running it.
+    pub fn compile_with_bindings(&self, code: &str, bindings: &Bindings)
+        -> Result<Compiled, EvalError>
+    {
+        let template = Template {
+            bindings: bindings_source(bindings)?,
+            ..self.template()
+        };
+        self.compile_template(&Header::default(), &template, code)
+    }
+
+    /// Returns the type of the expression without running the code.
+    ///
+    /// The code is only type-checked (with `--emit=metadata`), which is
+    /// faster than building it. The type is named as by rustc, e.g.
+    /// `Vec<i32>`, which can differ from `EvalOutput::type_name`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use everust::Evaluator;
+    /// let ty = Evaluator::new().type_of("vec![(1, 'a')]").unwrap();
+    /// assert_eq!("Vec<(i32, char)>", ty);
+    /// ```
+    pub fn type_of(&self, code: &str) -> Result<String, EvalError> {
+        self.type_of_with_prelude(&Header::default(), "", code)
+    }
+
+    /// Evaluates rust code and converts the value of the expression to `T`.
+    ///
+    /// The value is encoded as JSON by the program and decoded with serde, so
+    /// the expression can be of a primitive type, a string, a tuple, an
+    /// array, a `Vec`, an `Option`, a `Result`, a set, a map with string or
+    /// integer keys, or a combination of those. Other types fail to build.
+    /// When the value cannot be converted to `T`,
+    /// `EvalError::ValueMismatch` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use everust::Evaluator;
+    /// let v: Vec<(String, u32)> = Evaluator::new()
+    ///     .eval_as(r#"vec![("a".to_string(), 1)]"#)
+    ///     .unwrap();
+    /// assert_eq!(vec![("a".to_string(), 1)], v);
+    /// ```
+    pub fn eval_as<T: DeserializeOwned>(&self, code: &str)
+        -> Result<T, EvalError>
+    {
+        let template = Template {
+            format: ValueFormat::Json,
+            ..self.template()
+        };
+        let output = self.compile_template(&Header::default(), &template,
+            code)?.run()?;
+        serde_json::from_str(&output.value).map_err(|e| {
+            EvalError::ValueMismatch {
+                value: output.value,
+                message: e.to_string(),
+            }
+        })
+    }
+
+    /// Evaluates rust code, passing what the program writes to stdout and
+    /// stderr to `on_output` as it runs.
+    ///
+    /// See `Compiled::run_streaming`.
+    pub fn eval_streaming<F>(&self, code: &str, on_output: F)
+        -> Result<EvalOutput, EvalError>
+    where
+        F: FnMut(OutputChunk),
+    {
+        self.compile(code)?.run_streaming(&RunOptions::new(), on_output)
+    }
+
+    /// Evaluates rust code in a background thread.
+    ///
+    /// The returned handle can be used to cancel the evaluation, in which
+    /// case it returns `EvalError::Cancelled`.
+    pub fn spawn_eval(&self, code: &str) -> EvalHandle {
+        let token = CancelToken::new();
+        let mut evaluator = self.clone();
+        evaluator.cancel = Some(token.clone());
+        let code = code.to_owned();
+        let thread = thread::spawn(move || evaluator.eval(&code));
+        EvalHandle::new(token, thread)
+    }
+
+    /// Async version of `eval`.
+    ///
+    /// If the returned future is dropped before completion, rustc (or cargo)
+    /// or the running program is killed, along with the processes it spawned.
+    /// The future must be polled within a Tokio runtime with time and I/O
+    /// enabled.
+    #[cfg(feature = "tokio")]
+    pub async fn eval_async(&self, code: &str)
+        -> Result<EvalOutput, EvalError>
+    {
+        self.compile_async(code).await?.run_async().await
+    }
+
+    /// Async version of `compile`. See `eval_async` for details.
+    #[cfg(feature = "tokio")]
+    pub async fn compile_async(&self, code: &str)
+        -> Result<Compiled, EvalError>
+    {
+        let (evaluator, code) = self.apply_header(&Header::default(), code)?;
+        evaluator.compile_body_async(&evaluator.template(), &code).await
+    }
+
+    /// Builds rust code that runs `prelude` first.
+    ///
+    /// The settings of `prelude_header` and of the header of `code` are
+    /// applied. What the prelude writes to stdout and stderr is not returned
+    /// when the program runs.
+    pub(crate) fn compile_with_prelude(&self, prelude_header: &Header,
+        prelude: &str, code: &str) -> Result<Compiled, EvalError>
+    {
+        let template = Template {
+            prelude: prelude.to_owned(),
+            ..self.template()
+        };
+        self.compile_template(prelude_header, &template, code)
+    }
+
+    /// Returns the template formatting the value with the output format.
+    fn template(&self) -> Template {
+        Template {
+            prelude: String::new(),
+            bindings: String::new(),
+            format: ValueFormat::Text(self.output_format.clone()),
+        }
+    }
+
+    /// Returns the type of the expression of `code` run after `prelude`.
+    ///
+    /// See `compile_with_prelude`.
+    pub(crate) fn type_of_with_prelude(&self, prelude_header: &Header,
+        prelude: &str, code: &str) -> Result<String, EvalError>
+    {
+        let template = Template {
+            prelude: prelude.to_owned(),
+            bindings: String::new(),
+            format: ValueFormat::TypeCheck,
+        };
+        let (evaluator, code) = self.apply_header(prelude_header, code)?;
+        let job = evaluator.start_compile(&template, &code)?;
+        let out = evaluator.build_step(job.dir(), true)?
+            .run(evaluator.cancel())?;
+        evaluator.finish_check(&job, out)
+    }
+
+    /// Builds rust code in the given template, with the settings of
+    /// `prelude_header` and of the header of `code`.
+    fn compile_template(&self, prelude_header: &Header, template: &Template,
+        code: &str) -> Result<Compiled, EvalError>
+    {
+        let (evaluator, code) = self.apply_header(prelude_header, code)?;
+        evaluator.compile_body(template, &code)
+    }
+
+    /// Returns a copy of the evaluator with the settings of `prelude_header`
+    /// and of the header of `code`, and the code without its header.
+    fn apply_header(&self, prelude_header: &Header, code: &str)
+        -> Result<(Evaluator, String), EvalError>
+    {
+        let (header, code) = header::split(code).map_err(|e| {
+            EvalError::Build(vec![Diagnostic::unstructured(e)])
+        })?;
+        if !header.dependencies.is_empty() && !self.header_dependencies {
+            return Err(EvalError::Build(vec![Diagnostic::unstructured(
+                "Dependencies in the header are not enabled (see \
+                EvaluatorBuilder::header_dependencies)".to_owned())]))
+        }
+        let mut merged = prelude_header.clone();
+        merged.merge(header);
+        if merged.is_empty() {
+            Ok((self.clone(), code))
+        } else {
+            Ok((self.with_header(merged), code))
+        }
+    }
+
+    /// Returns a copy of the evaluator with the settings of a header.
+    ///
+    /// Dependencies enable the Cargo backend if needed. They must have been
+    /// allowed with `EvaluatorBuilder::header_dependencies`.
+    fn with_header(&self, header: Header) -> Evaluator {
+        let mut evaluator = self.clone();
+        if header.edition.is_some() {
+            evaluator.edition = header.edition;
+        }
+        if !header.dependencies.is_empty() {
+            let cargo = evaluator.cargo.take().unwrap_or_default();
+            evaluator.cargo = Some(header.dependencies.into_iter()
+                .fold(cargo, Cargo::dependency));
+        }
+        evaluator
+    }
+
+    fn compile_body(&self, template: &Template, code: &str)
+        -> Result<Compiled, EvalError>
+    {
+        let mut job = self.start_compile(template, code)?;
+        let mut versions = Vec::new();
+        for step in self.version_steps() {
+            let spawn_error = step.spawn_error;
+            versions.push(version(step.run(self.cancel())?, spawn_error)?);
+        }
+        self.look_up(&mut job, versions)?;
+        if !job.cached {
+            let out = self.build_step(job.dir(), false)?
+                .run(self.cancel())?;
+            self.finish_build(&job, out)?;
+        }
+        self.finish_compile(job)
+    }
+
+    /// Async version of `compile_body`.
+    #[cfg(feature = "tokio")]
+    async fn compile_body_async(&self, template: &Template, code: &str)
+        -> Result<Compiled, EvalError>
+    {
+        let mut job = self.start_compile(template, code)?;
+        let mut versions = Vec::new();
+        for step in self.version_steps() {
+            let spawn_error = step.spawn_error;
+            versions.push(version(step.run_async().await?, spawn_error)?);
+        }
+        self.look_up(&mut job, versions)?;
+        if !job.cached {
+            let out = self.build_step(job.dir(), false)?.run_async().await?;
+            self.finish_build(&job, out)?;
+        }
+        self.finish_compile(job)
+    }
+
+    /// Writes the source file of the program to a new temporary directory.
+    fn start_compile(&self, template: &Template, code: &str)
+        -> Result<CompileJob, EvalError>
+    {
+        let temp = TempDir::new("everust")
+            .map_err(OtherError::CreateTempDir)?;
+        let source = Source::new(code, template);
+        source.write_to(&temp.path().join(source::FILE_NAME))
+            .map_err(OtherError::WriteSrcFile)?;
+        Ok(CompileJob {
+            temp,
+            source,
+            start: Instant::now(),
+            key: None,
+            cached: false,
+        })
+    }
+
+    /// Returns the steps printing the versions of the tools, which are part
+    /// of the cache key.
+    fn version_steps(&self) -> Vec<Step> {
+        if self.cache.is_none() {
+            return Vec::new()
+        }
+        let mut steps = Vec::new();
+        let rustc = match self.cargo {
+            Some(ref cargo) => {
+                steps.push(self.version_step(cargo.path(), spawn_cargo_error));
+                env::var_os("RUSTC").map_or(PathBuf::from("rustc"),
+                    PathBuf::from)
+            }
+            None => self.rustc.clone(),
+        };
+        steps.push(self.version_step(&rustc, spawn_rustc_error));
+        steps
+    }
+
+    /// Returns the step printing the verbose version of a tool (e.g.
+    /// `rustc -vV`).
+    fn version_step(&self, tool: &Path, spawn_error: fn(io::Error) -> EvalError)
+        -> Step
+    {
+        let mut cmd = Command::new(tool);
+        cmd.arg("-vV").envs(self.envs.iter().map(|(k, v)| (k, v)));
+        Step {
+            phase: Phase::Compile,
+            cmd,
+            stdin: None,
+            timeout: self.compile_timeout,
+            spawn_error,
+        }
+    }
+
+    /// Copies the program from the cache if it is there.
+    fn look_up(&self, job: &mut CompileJob, versions: Vec<Vec<u8>>)
+        -> Result<(), EvalError>
+    {
+        let cache = match self.cache {
+            Some(ref cache) => cache,
+            None => return Ok(()),
+        };
+        let key = self.cache_key(&job.source, versions)
+            .map_err(OtherError::Cache)?;
+        job.cached = cache.get(&key, &job.dir().join(PROGRAM_NAME))
+            .map_err(OtherError::Cache)?;
+        job.key = Some(key);
+        Ok(())
+    }
+
+    /// Returns the step building the program `PROGRAM_NAME` from the source
+    /// file in `dir`.
+    ///
+    /// If `check` is true, the program is only type-checked, with full type
+    /// names in diagnostics.
+    fn build_step(&self, dir: &Path, check: bool) -> Result<Step, EvalError> {
+        let (mut cmd, spawn_error) = match self.cargo {
+            Some(ref cargo) => {
+                self.cargo_base().and_then(|base| {
+                    cargo::write_package(dir, cargo, self.edition,
+                        PROGRAM_NAME, source::FILE_NAME, &base)
+                }).map_err(OtherError::WriteManifest)?;
+                let mut cmd = cargo::command(dir, cargo, PROGRAM_NAME, check);
+                self.rustc_args(&mut cmd, dir);
+                (cmd, spawn_cargo_error as fn(io::Error) -> EvalError)
+            }
+            None => {
+                let mut cmd = Command::new(&self.rustc);
+                cmd.current_dir(dir).arg("--error-format=json");
+                if let Some(edition) = self.edition {
+                    cmd.arg("--edition").arg(edition.as_str());
+                }
+                if check {
+                    cmd.arg("--emit=metadata");
+                }
+                self.rustc_args(&mut cmd, dir);
+                cmd.arg("-o").arg(PROGRAM_NAME).arg(source::FILE_NAME);
+                (cmd, spawn_rustc_error as fn(io::Error) -> EvalError)
+            }
+        };
+        if check {
+            cmd.arg("--verbose");
+        }
+        Ok(Step {
+            phase: Phase::Compile,
+            cmd,
+            stdin: None,
+            timeout: self.compile_timeout,
+            spawn_error,
+        })
+    }
+
+    /// Checks the outcome of the build step.
+    fn finish_build(&self, job: &CompileJob, out: Finished)
+        -> Result<(), EvalError>
+    {
+        let result = if self.cargo.is_none() {
+            if out.status.success() {
+                return Ok(())
+            }
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            Err(diagnostic::parse(&stderr))
+        } else {
+            let build = cargo::parse(&String::from_utf8_lossy(&out.stdout),
+                &String::from_utf8_lossy(&out.stderr));
+            if out.status.success() {
+                let executable = build.executable.ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound,
+                        "cargo did not report the executable")
+                });
+                executable
+                    .and_then(|executable| {
+                        fs::copy(executable, job.dir().join(PROGRAM_NAME))
+                    })
+                    .map_err(OtherError::CopyProgram)?;
+                return Ok(())
+            }
+            Err(build.diagnostics)
+        };
+        result.map_err(|mut diagnostics| {
+            job.source.map.map_diagnostics(&mut diagnostics);
+            EvalError::Build(diagnostics)
+        })
+    }
+
+    /// Returns the type of the expression from the outcome of a type check.
+    fn finish_check(&self, job: &CompileJob, out: Finished)
+        -> Result<String, EvalError>
+    {
+        let mut diagnostics = if self.cargo.is_none() {
+            diagnostic::parse(&String::from_utf8_lossy(&out.stderr))
+        } else {
+            cargo::parse(&String::from_utf8_lossy(&out.stdout),
+                &String::from_utf8_lossy(&out.stderr)).diagnostics
+        };
+        let ty = job.source.map.take_type(&mut diagnostics);
+        // Once the error reporting the type is removed, other errors have
+        // spans, unlike the summary of the errors.
+        let failed = diagnostics.iter().any(|diagnostic| {
+            diagnostic.level == Level::Error && !diagnostic.spans.is_empty()
+        });
+        match ty {
+            Some(ty) if !failed => Ok(ty),
+            None if out.status.success() => Ok("()".to_owned()),
+            _ => {
+                job.source.map.map_diagnostics(&mut diagnostics);
+                Err(EvalError::Build(diagnostics))
+            }
+        }
+    }
+
+    /// Stores the program in the cache if needed and returns it.
+    fn finish_compile(&self, job: CompileJob) -> Result<Compiled, EvalError> {
+        if let (Some(cache), Some(key), false) =
+            (self.cache.as_ref(), job.key.as_ref(), job.cached)
+        {
+            cache.put(key, &job.dir().join(PROGRAM_NAME))
+                .map_err(OtherError::Cache)?;
+        }
+        let compile_time = job.start.elapsed();
+        Ok(Compiled::new(self.clone(), job.temp, job.source.map, compile_time,
+            job.cached))
+    }
+
+    /// Returns the key of the program built from `source` in the cache.
+    ///
+    /// `versions` contains the outputs of the steps returned by
+    /// `version_steps`.
+    /// The Cargo configuration is part of the key with its paths resolved,
+    /// along with the size and modification time of the files of local
+    /// crates.
+    fn cache_key(&self, source: &Source, mut versions: Vec<Vec<u8>>)
+        -> io::Result<String>
+    {
+        let options = format!("{:?}", (&self.rustc, self.edition,
+            &self.codegen_options, &self.cfgs, &self.envs));
+        if let Some(ref cargo) = self.cargo {
+            versions.push(cargo::fingerprint(cargo, &self.cargo_base()?)?
+                .into_bytes());
+            for var in &["RUSTC", "RUSTFLAGS"] {
+                let value = env::var_os(var).unwrap_or_default();
+                versions.push(value.to_string_lossy().into_owned()
+                    .into_bytes());
+            }
+        }
+        let parts = [source.text.as_bytes(), options.as_bytes()].iter()
+            .cloned()
+            .chain(versions.iter().map(|v| &v[..]))
+            .collect::<Vec<_>>();
+        Ok(cache::key(parts))
+    }
+
+    /// Returns the directory against which relative paths of the Cargo
+    /// configuration are resolved.
+    fn cargo_base(&self) -> io::Result<PathBuf> {
+        let cwd = env::current_dir()?;
+        Ok(match self.current_dir {
+            Some(ref dir) => cwd.join(dir),
+            None => cwd,
+        })
+    }
+
+    /// Adds the arguments shared by the rustc and cargo backends.
+    fn rustc_args(&self, cmd: &mut Command, dir: &Path) {
+        let mut remap = OsString::from(dir);
+        remap.push("=.");
+        cmd.arg("--remap-path-prefix").arg(remap);
+        for option in &self.codegen_options {
+            cmd.arg("-C").arg(option);
+        }
+        for cfg in &self.cfgs {
+            cmd.arg("--cfg").arg(cfg);
+        }
+        cmd.envs(self.envs.iter().map(|(k, v)| (k, v)));
+    }
+
+    /// Returns the command running the program in `dir`.
+    pub(crate) fn program_command(&self, dir: &Path, options: &RunOptions)
+        -> io::Result<Command>
+    {
+        let run_dir = match self.sandbox {
+            Some(_) => Path::new(sandbox::WORK_DIR),
+            None => dir,
+        };
+        let mut cmd = Command::new(run_dir.join(PROGRAM_NAME));
+        cmd.args(options.get_args());
+        match (&self.sandbox, &self.current_dir) {
+            (Some(sandbox), _) => sandbox::apply(&mut cmd, sandbox, dir)?,
+            (None, Some(dir)) => {
+                cmd.current_dir(dir);
+            }
+            (None, None) => {}
+        }
+        if options.get_env_clear() {
+            cmd.env_clear();
+        }
+        cmd.envs(self.envs.iter().map(|(k, v)| (k, v)))
+            .envs(options.get_envs().iter().map(|(k, v)| (k, v)))
+            .env(source::VALUE_ENV, run_dir.join(source::VALUE_FILE_NAME))
+            .env(source::TYPE_ENV, run_dir.join(source::TYPE_FILE_NAME));
+        limits::apply(&mut cmd, &self.limits)?;
+        Ok(cmd)
+    }
+
+    pub(crate) fn sandbox(&self) -> Option<&Sandbox> {
+        self.sandbox.as_ref()
+    }
+
+    pub(crate) fn seccomp(&self) -> Option<&SeccompProfile> {
+        self.seccomp.as_ref()
+    }
+
+    pub(crate) fn resource_limits(&self) -> &ResourceLimits {
+        &self.limits
+    }
+
+    pub(crate) fn run_timeout(&self) -> Option<Duration> {
+        self.run_timeout
+    }
+
+    pub(crate) fn run_options(&self) -> &RunOptions {
+        &self.run_options
+    }
+
+    pub(crate) fn set_output_format(&mut self, format: OutputFormat) {
+        self.output_format = format;
+    }
+
+    pub(crate) fn cancel(&self) -> Option<&CancelToken> {
+        self.cancel.as_ref()
+    }
+}
+
+impl Default for Evaluator {
+    fn default() -> Evaluator {
+        Evaluator::new()
+    }
+}
+
+/// Builder for `Evaluator`.
+#[derive(Clone, Debug)]
+pub struct EvaluatorBuilder {
+    evaluator: Evaluator,
+}
+
+impl EvaluatorBuilder {
+    /// Returns a builder starting from the default configuration.
+    pub fn new() -> EvaluatorBuilder {
+        EvaluatorBuilder {evaluator: Evaluator::new()}
+    }
+
+    /// Sets the path to the rustc binary.
+    ///
+    /// A path without separators is looked up in the PATH.
+    pub fn rustc<P: Into<PathBuf>>(mut self, path: P) -> EvaluatorBuilder {
+        self.evaluator.rustc = path.into();
+        self
+    }
+
+    /// Sets the edition used to build the code.
+    pub fn edition(mut self, edition: Edition) -> EvaluatorBuilder {
+        self.evaluator.edition = Some(edition);
+        self
+    }
+
+    /// Adds a codegen option passed to rustc with `-C` (e.g. `opt-level=2`).
+    pub fn codegen_option<S: Into<String>>(mut self, option: S)
+        -> EvaluatorBuilder
+    {
+        self.evaluator.codegen_options.push(option.into());
+        self
+    }
+
+    /// Adds a configuration flag passed to rustc with `--cfg` (e.g.
+    /// `feature="foo"`).
+    pub fn cfg<S: Into<String>>(mut self, cfg: S) -> EvaluatorBuilder {
+        self.evaluator.cfgs.push(cfg.into());
+        self
+    }
+
+    /// Sets an environment variable for rustc and the evaluated program.
+    pub fn env<K, V>(mut self, key: K, value: V) -> EvaluatorBuilder
+    where
+        K: Into<OsString>,
+        V: Into<OsString>,
+    {
+        self.evaluator.envs.push((key.into(), value.into()));
+        self
+    }
+
+    /// Sets the working directory of the evaluated program.
+    ///
+    /// By default, the program inherits the working directory of the current
+    /// process. This is ignored when the program runs in a sandbox. Relative
+    /// paths of dependencies are resolved against this directory.
+    pub fn current_dir<P: Into<PathBuf>>(mut self, dir: P) -> EvaluatorBuilder {
+        self.evaluator.current_dir = Some(dir.into());
+        self
+    }
+
+    /// Sets the maximum time rustc is allowed to run.
+    ///
+    /// When it is exceeded, rustc is killed and `EvalError::Timeout` is
+    /// returned. There is no timeout by default.
+    pub fn compile_timeout(mut self, timeout: Duration) -> EvaluatorBuilder {
+        self.evaluator.compile_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum time the evaluated program is allowed to run.
+    ///
+    /// When it is exceeded, the program and the processes it spawned are
+    /// killed and `EvalError::Timeout` is returned. There is no timeout by
+    /// default.
+    pub fn run_timeout(mut self, timeout: Duration) -> EvaluatorBuilder {
+        self.evaluator.run_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the resource limits applied to the evaluated program.
+    ///
+    /// When the program fails because of one of these limits,
+    /// `EvalError::ResourceLimitExceeded` is returned. Resource limits are only
+    /// supported on Linux.
+    pub fn resource_limits(mut self, limits: ResourceLimits)
+        -> EvaluatorBuilder
+    {
+        self.evaluator.limits = limits;
+        self
+    }
+
+    /// Runs the evaluated program in a sandbox.
+    ///
+    /// When the sandbox cannot be set up, `EvalError::SandboxUnavailable` is
+    /// returned. See `Sandbox` for details.
+    pub fn sandbox(mut self, sandbox: Sandbox) -> EvaluatorBuilder {
+        self.evaluator.sandbox = Some(sandbox);
+        self
+    }
+
+    /// Applies a seccomp filter to the evaluated program.
+    ///
+    /// When the program makes a forbidden system call,
+    /// `EvalError::SyscallBlocked` is returned. See `SeccompProfile` for
+    /// details.
+    pub fn seccomp(mut self, profile: SeccompProfile) -> EvaluatorBuilder {
+        self.evaluator.seccomp = Some(profile);
+        self
+    }
+
+    /// Caches compiled programs on disk. See `Cache` for details.
+    pub fn cache(mut self, cache: Cache) -> EvaluatorBuilder {
+        self.evaluator.cache = Some(cache);
+        self
+    }
+
+    /// Builds the code with Cargo instead of rustc, so that it can use
+    /// external crates.
+    ///
+    /// The rustc path set with `rustc` is then ignored. See `Cargo` for
+    /// details.
+    pub fn cargo(mut self, cargo: Cargo) -> EvaluatorBuilder {
+        self.evaluator.cargo = Some(cargo);
+        self
+    }
+
+    /// Lets the evaluated code declare dependencies in its header (see
+    /// `everust::eval`).
+    ///
+    /// The dependencies are built with the Cargo backend, which is used with
+    /// its default configuration if `cargo` was not called. Code declaring
+    /// dependencies is rejected with `EvalError::Build` unless this is
+    /// enabled, since building them runs their build scripts outside the
+    /// sandbox and seccomp filter. Disabled by default.
+    pub fn header_dependencies(mut self, enabled: bool) -> EvaluatorBuilder {
+        self.evaluator.header_dependencies = enabled;
+        self
+    }
+
+    /// Sets the input, arguments and environment of every run of the
+    /// evaluated program.
+    ///
+    /// Options passed to `Compiled::run_with` are added to these. By
+    /// default, the program has no input or arguments and inherits the
+    /// environment of the current process.
+    pub fn run_options(mut self, options: RunOptions) -> EvaluatorBuilder {
+        self.evaluator.run_options = options;
+        self
+    }
+
+    /// Sets how the value of the expression is formatted.
+    ///
+    /// The default is `OutputFormat::Debug`. `EvalOutput::parse_value`
+    /// expects `Debug` or `PrettyDebug` output.
+    pub fn output_format(mut self, format: OutputFormat) -> EvaluatorBuilder {
+        self.evaluator.output_format = format;
+        self
+    }
+
+    /// Returns the configured evaluator.
+    pub fn build(self) -> Evaluator {
+        self.evaluator
+    }
+}
+
+impl Default for EvaluatorBuilder {
+    fn default() -> EvaluatorBuilder {
+        EvaluatorBuilder::new()
+    }
+}
+
+/// Program being built.
+struct CompileJob {
+    temp: TempDir,
+    source: Source,
+    start: Instant,
+    key: Option<String>,
+    cached: bool,
+}
+
+impl CompileJob {
+    fn dir(&self) -> &Path {
+        self.temp.path()
+    }
+}
+
+/// Child process run during an evaluation.
+pub(crate) struct Step {
+    pub(crate) phase: Phase,
+    pub(crate) cmd: Command,
+    pub(crate) stdin: Option<Vec<u8>>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) spawn_error: fn(io::Error) -> EvalError,
+}
+
+impl Step {
+    pub(crate) fn run(self, cancel: Option<&CancelToken>)
+        -> Result<Finished, EvalError>
+    {
+        self.run_with_output(cancel, None)
+    }
+
+    /// Runs the step, passing the output of the process to `on_output` as it
+    /// is written.
+    pub(crate) fn run_with_output(self, cancel: Option<&CancelToken>,
+        on_output: Option<&mut dyn FnMut(OutputChunk)>)
+        -> Result<Finished, EvalError>
+    {
+        let result = process::run(self.cmd, self.stdin, self.timeout,
+            cancel.map(CancelToken::flag), on_output);
+        outcome(self.phase, result, self.spawn_error)
+    }
+
+    /// Runs the step asynchronously. Dropping the returned future kills the
+    /// processes involved.
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn run_async(self) -> Result<Finished, EvalError> {
+        let result = process::run_async(self.cmd, self.stdin, self.timeout)
+            .await;
+        outcome(self.phase, result, self.spawn_error)
+    }
+}
+
+fn outcome(phase: Phase, result: Result<Outcome, RunError>,
+    spawn_error: fn(io::Error) -> EvalError) -> Result<Finished, EvalError>
+{
+    match result {
+        Ok(Outcome::Finished(out)) => Ok(out),
+        Ok(Outcome::TimedOut(elapsed)) => {
+            Err(EvalError::Timeout {phase, elapsed})
+        }
+        Ok(Outcome::Cancelled) => Err(EvalError::Cancelled),
+        Err(RunError::Spawn(e)) => Err(spawn_error(e)),
+        Err(RunError::Wait(e)) => Err(OtherError::Wait(e).into()),
+    }
+}
+
+/// Returns the statements declaring the bound variables.
+fn bindings_source(bindings: &Bindings) -> Result<String, EvalError> {
+    bindings.to_source().map_err(|e| {
+        EvalError::Build(vec![Diagnostic::unstructured(e)])
+    })
+}
+
+/// Returns the version printed by a tool.
+fn version(out: Finished, spawn_error: fn(io::Error) -> EvalError)
+    -> Result<Vec<u8>, EvalError>
+{
+    if !out.status.success() {
+        let e = io::Error::other(String::from_utf8_lossy(&out.stderr)
+            .into_owned());
+        return Err(spawn_error(e))
+    }
+    Ok(out.stdout)
+}
+
+fn spawn_rustc_error(e: io::Error) -> EvalError {
+    OtherError::SpawnRustc(e).into()
+}
+
+fn spawn_cargo_error(e: io::Error) -> EvalError {
+    OtherError::SpawnCargo(e).into()
+}
+
+pub(crate) fn spawn_prog_error(e: io::Error) -> EvalError {
+    OtherError::SpawnProg(e).into()
+}
@@ -0,0 +1,118 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+/// How the value of the expression is formatted in `EvalOutput::value`.
+///
+/// When the expression does not implement the trait used by the format, the
+/// name of its type is written instead, as returned by `std::any::type_name`.
+/// `()` is written as an empty string. A custom format string is used as is,
+/// so the expression must implement the traits it uses.
+///
+/// # Examples
+///
+/// ```rust
+/// use everust::{Evaluator, OutputFormat};
+/// let evaluator = Evaluator::builder()
+///     .output_format(OutputFormat::Hex)
+///     .build();
+/// assert_eq!("0xff", evaluator.eval("255").unwrap().value);
+/// assert_eq!("f64", evaluator.eval("1.5").unwrap().value);
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub enum OutputFormat {
+    /// `Debug`, as with `{:?}`.
+    #[default]
+    Debug,
+    /// Pretty-printed `Debug`, as with `{:#?}`.
+    PrettyDebug,
+    /// `Display`, as with `{}`.
+    Display,
+    /// Hexadecimal integer with a `0x` prefix, as with `{:#x}`.
+    Hex,
+    /// Binary integer with a `0b` prefix, as with `{:#b}`.
+    Binary,
+    /// Format string with a single argument, e.g. `"{:>8.3}"`.
+    Custom(String),
+}
+
+impl OutputFormat {
+    /// Returns the format string passed to `format!`.
+    fn format_string(&self) -> &str {
+        match *self {
+            OutputFormat::Debug => "{:?}",
+            OutputFormat::PrettyDebug => "{:#?}",
+            OutputFormat::Display => "{}",
+            OutputFormat::Hex => "{:#x}",
+            OutputFormat::Binary => "{:#b}",
+            OutputFormat::Custom(ref s) => s,
+        }
+    }
+
+    /// Returns the trait used by the format, or `None` for a custom format.
+    fn format_trait(&self) -> Option<&'static str> {
+        match *self {
+            OutputFormat::Debug | OutputFormat::PrettyDebug => Some("Debug"),
+            OutputFormat::Display => Some("Display"),
+            OutputFormat::Hex => Some("LowerHex"),
+            OutputFormat::Binary => Some("Binary"),
+            OutputFormat::Custom(_) => None,
+        }
+    }
+
+    /// Returns the expression formatting `expr` in the generated source.
+    pub(crate) fn wrapper_value(&self) -> String {
+        match self.format_trait() {
+            Some(_) => "{ use self::__everust_format::*; \
+                (&&&Wrap(&expr)).format_value() }".to_owned(),
+            None => format!("format!({:?}, expr)", self.format_string()),
+        }
+    }
+
+    /// Returns code to insert at the top level of the generated source to
+    /// format values.
+    ///
+    /// The method called on the value is chosen by autoref specialization:
+    /// `()` is matched first, then values implementing the trait of the
+    /// format, and any other value is replaced by the name of its type.
+    pub(crate) fn wrapper_items(&self) -> String {
+        let format_trait = match self.format_trait() {
+            Some(format_trait) => format_trait,
+            None => return String::new(),
+        };
+        format!(r##"
+#[allow(dead_code)]
+mod __everust_format {{
+    pub struct Wrap<'a, T>(pub &'a T);
+
+    pub trait Unit {{
+        fn format_value(&self) -> String;
+    }}
+
+    impl<'a> Unit for &&Wrap<'a, ()> {{
+        fn format_value(&self) -> String {{
+            String::new()
+        }}
+    }}
+
+    pub trait Formatted {{
+        fn format_value(&self) -> String;
+    }}
+
+    impl<'a, T: ::std::fmt::{}> Formatted for &Wrap<'a, T> {{
+        fn format_value(&self) -> String {{
+            format!({:?}, self.0)
+        }}
+    }}
+
+    pub trait TypeName {{
+        fn format_value(&self) -> String;
+    }}
+
+    impl<'a, T> TypeName for Wrap<'a, T> {{
+        fn format_value(&self) -> String {{
+            ::std::any::type_name::<T>().to_owned()
+        }}
+    }}
+}}
+"##, format_trait, self.format_string())
+    }
+}
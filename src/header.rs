@@ -0,0 +1,207 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+use crate::cargo::Dependency;
+use crate::evaluator::Edition;
+
+/// Settings declared in the header of the evaluated code.
+#[derive(Clone, Debug, Default)]
+pub struct Header {
+    pub dependencies: Vec<Dependency>,
+    pub edition: Option<Edition>,
+}
+
+impl Header {
+    pub fn is_empty(&self) -> bool {
+        self.dependencies.is_empty() && self.edition.is_none()
+    }
+
+    /// Adds the settings of `other`, which take precedence.
+    pub fn merge(&mut self, other: Header) {
+        self.dependencies.extend(other.dependencies);
+        self.edition = other.edition.or(self.edition);
+    }
+}
+
+/// Splits the header from code.
+///
+/// The header is either a frontmatter block:
+///
+/// ```text
+/// ---cargo
+/// [dependencies]
+/// regex = "1"
+/// ---
+/// ```
+///
+/// or a `cargo` code block in inner doc comments:
+///
+/// ```text
+/// //! ```cargo
+/// //! [dependencies]
+/// //! regex = "1"
+/// //! ```
+/// ```
+///
+/// Its content is a manifest where only `[dependencies]` and
+/// `package.edition` are used. The returned code has the header replaced with
+/// whitespace, so that positions in the code are unchanged.
+pub fn split(code: &str) -> Result<(Header, String), String> {
+    let mut lines = code.split_inclusive('\n').peekable();
+    let mut header_len = 0;
+    if let Some(line) = lines.peek() {
+        if line.starts_with("#!") && !line.starts_with("#![") {
+            header_len += line.len();
+            lines.next();
+        }
+    }
+    while let Some(line) = lines.next_if(|line| line.trim().is_empty()) {
+        header_len += line.len();
+    }
+    let manifest = match lines.peek() {
+        Some(line) if line.starts_with("---") => {
+            match frontmatter(lines)? {
+                Some((manifest, len)) => {
+                    header_len += len;
+                    Some(manifest)
+                }
+                None => None,
+            }
+        }
+        Some(line) if line.trim_start().starts_with("//!") => {
+            let (manifest, len) = doc_block(lines)?;
+            header_len += len;
+            manifest
+        }
+        _ => None,
+    };
+    let header = match manifest {
+        Some(manifest) => parse_manifest(&manifest)?,
+        None => return Ok((Header::default(), code.to_owned())),
+    };
+    let (header_text, body) = code.split_at(header_len);
+    let blank = header_text.chars()
+        .map(|c| match c {
+            '\n' | '\r' => c.to_string(),
+            c => " ".repeat(c.len_utf8()),
+        })
+        .collect::<String>();
+    Ok((header, blank + body))
+}
+
+/// Parses a frontmatter block. Returns the manifest and the length of the
+/// block, or `None` if the lines do not start with a frontmatter block (e.g.
+/// `---5` is an expression).
+fn frontmatter<'a, I>(mut lines: I) -> Result<Option<(String, usize)>, String>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let opening = lines.next().unwrap_or("");
+    let dashes = opening.chars().take_while(|&c| c == '-').count();
+    let info = opening[dashes..].trim();
+    if !info.is_empty() && !is_info_string(info) {
+        return Ok(None)
+    }
+    let mut len = opening.len();
+    let mut manifest = String::new();
+    for line in lines {
+        len += line.len();
+        let trimmed = line.trim_end();
+        if trimmed.len() == dashes && trimmed.chars().all(|c| c == '-') {
+            if !info.is_empty() && info != "cargo" {
+                return Err(format!("Unsupported frontmatter `{}`", info))
+            }
+            return Ok(Some((manifest, len)))
+        }
+        manifest.push_str(line);
+    }
+    Ok(None)
+}
+
+/// Checks if the text after the opening dashes of a frontmatter block is an
+/// identifier, like `cargo`.
+fn is_info_string(s: &str) -> bool {
+    let mut chars = s.chars();
+    let valid_start = match chars.next() {
+        Some(c) => c == '_' || c.is_alphabetic(),
+        None => false,
+    };
+    valid_start && chars.all(|c| "_-.".contains(c) || c.is_alphanumeric())
+}
+
+/// Parses leading inner doc comments. Returns the manifest of the `cargo`
+/// code block, if any, and the length of the doc comments.
+fn doc_block<'a, I>(lines: I) -> Result<(Option<String>, usize), String>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let mut len = 0;
+    let mut manifest: Option<String> = None;
+    let mut in_block = false;
+    for line in lines {
+        let content = match line.trim_start().strip_prefix("//!") {
+            Some(content) => content,
+            None => break,
+        };
+        len += line.len();
+        let content = content.strip_prefix(' ').unwrap_or(content);
+        let fence = content.trim();
+        if in_block {
+            if fence == "```" {
+                in_block = false;
+            } else if let Some(ref mut manifest) = manifest {
+                manifest.push_str(content.trim_end_matches(['\r', '\n']));
+                manifest.push('\n');
+            }
+        } else if fence == "```cargo" && manifest.is_none() {
+            in_block = true;
+            manifest = Some(String::new());
+        }
+    }
+    if in_block {
+        return Err("Unclosed ```cargo block".to_owned())
+    }
+    Ok((manifest, len))
+}
+
+fn parse_manifest(manifest: &str) -> Result<Header, String> {
+    let invalid = |e: String| format!("Invalid header: {}", e);
+    let table = manifest.parse::<toml::Table>()
+        .map_err(|e| invalid(e.message().to_owned()))?;
+    let mut header = Header::default();
+    for (key, value) in table {
+        match &key[..] {
+            "dependencies" => {
+                let dependencies = match value {
+                    toml::Value::Table(dependencies) => dependencies,
+                    _ => return Err(invalid("`dependencies` must be a \
+                        table".to_owned())),
+                };
+                for (name, spec) in dependencies {
+                    let dependency = Dependency::from_toml(name, spec)
+                        .map_err(invalid)?;
+                    header.dependencies.push(dependency);
+                }
+            }
+            "package" => {
+                let edition = value.get("edition").map(|edition| {
+                    edition.as_str().and_then(parse_edition)
+                        .ok_or_else(|| invalid(format!("Unknown edition {}",
+                            edition)))
+                });
+                header.edition = edition.transpose()?;
+            }
+            _ => return Err(invalid(format!("Unsupported key `{}`", key))),
+        }
+    }
+    Ok(header)
+}
+
+fn parse_edition(s: &str) -> Option<Edition> {
+    match s {
+        "2015" => Some(Edition::E2015),
+        "2018" => Some(Edition::E2018),
+        "2021" => Some(Edition::E2021),
+        "2024" => Some(Edition::E2024),
+        _ => None,
+    }
+}
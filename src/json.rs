@@ -0,0 +1,275 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+//! JSON encoding of the value of the expression, used by `eval_as`.
+
+/// Returns the expression formatting `expr` as JSON in the generated source.
+pub fn wrapper_value() -> &'static str {
+    "__everust_json::to_string(&expr)"
+}
+
+/// Returns code to insert at the top level of the generated source to encode
+/// values as JSON.
+pub fn wrapper_items() -> &'static str {
+    r##"
+#[allow(dead_code)]
+mod __everust_json {
+    use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+    use std::fmt::Write;
+
+    pub fn to_string<T: ?Sized + Json>(value: &T) -> String {
+        let mut out = String::new();
+        value.write_json(&mut out);
+        out
+    }
+
+    pub trait Json {
+        fn write_json(&self, out: &mut String);
+    }
+
+    pub trait JsonKey {
+        fn write_key(&self, out: &mut String);
+    }
+
+    fn write_str(s: &str, out: &mut String) {
+        out.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                c if (c as u32) < 0x20 => {
+                    write!(out, "\\u{:04x}", c as u32).unwrap();
+                }
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+    }
+
+    fn write_seq<'a, T, I>(values: I, out: &mut String)
+    where
+        T: 'a + Json,
+        I: IntoIterator<Item = &'a T>,
+    {
+        out.push('[');
+        for (i, value) in values.into_iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            value.write_json(out);
+        }
+        out.push(']');
+    }
+
+    fn write_map<'a, K, V, I>(entries: I, out: &mut String)
+    where
+        K: 'a + JsonKey,
+        V: 'a + Json,
+        I: IntoIterator<Item = (&'a K, &'a V)>,
+    {
+        out.push('{');
+        for (i, (k, v)) in entries.into_iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            k.write_key(out);
+            out.push(':');
+            v.write_json(out);
+        }
+        out.push('}');
+    }
+
+    macro_rules! integers {
+        ($($t:ty),*) => {$(
+            impl Json for $t {
+                fn write_json(&self, out: &mut String) {
+                    write!(out, "{}", self).unwrap();
+                }
+            }
+
+            impl JsonKey for $t {
+                fn write_key(&self, out: &mut String) {
+                    write!(out, "\"{}\"", self).unwrap();
+                }
+            }
+        )*};
+    }
+
+    integers!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128,
+        usize);
+
+    macro_rules! floats {
+        ($($t:ty),*) => {$(
+            impl Json for $t {
+                fn write_json(&self, out: &mut String) {
+                    if self.is_finite() {
+                        write!(out, "{:?}", self).unwrap();
+                    } else {
+                        out.push_str("null");
+                    }
+                }
+            }
+        )*};
+    }
+
+    floats!(f32, f64);
+
+    impl Json for bool {
+        fn write_json(&self, out: &mut String) {
+            out.push_str(if *self { "true" } else { "false" });
+        }
+    }
+
+    impl Json for char {
+        fn write_json(&self, out: &mut String) {
+            write_str(self.encode_utf8(&mut [0; 4]), out);
+        }
+    }
+
+    impl Json for str {
+        fn write_json(&self, out: &mut String) {
+            write_str(self, out);
+        }
+    }
+
+    impl JsonKey for str {
+        fn write_key(&self, out: &mut String) {
+            write_str(self, out);
+        }
+    }
+
+    impl Json for String {
+        fn write_json(&self, out: &mut String) {
+            write_str(self, out);
+        }
+    }
+
+    impl JsonKey for String {
+        fn write_key(&self, out: &mut String) {
+            write_str(self, out);
+        }
+    }
+
+    impl Json for () {
+        fn write_json(&self, out: &mut String) {
+            out.push_str("null");
+        }
+    }
+
+    impl<'a, T: ?Sized + Json> Json for &'a T {
+        fn write_json(&self, out: &mut String) {
+            (**self).write_json(out);
+        }
+    }
+
+    impl<'a, T: ?Sized + JsonKey> JsonKey for &'a T {
+        fn write_key(&self, out: &mut String) {
+            (**self).write_key(out);
+        }
+    }
+
+    impl<T: ?Sized + Json> Json for Box<T> {
+        fn write_json(&self, out: &mut String) {
+            (**self).write_json(out);
+        }
+    }
+
+    impl<T: Json> Json for Option<T> {
+        fn write_json(&self, out: &mut String) {
+            match *self {
+                Some(ref value) => value.write_json(out),
+                None => out.push_str("null"),
+            }
+        }
+    }
+
+    impl<T: Json, E: Json> Json for Result<T, E> {
+        fn write_json(&self, out: &mut String) {
+            let (tag, value): (&str, &dyn Json) = match *self {
+                Ok(ref value) => ("Ok", value),
+                Err(ref e) => ("Err", e),
+            };
+            out.push('{');
+            write_str(tag, out);
+            out.push(':');
+            value.write_json(out);
+            out.push('}');
+        }
+    }
+
+    impl<T: Json> Json for [T] {
+        fn write_json(&self, out: &mut String) {
+            write_seq(self, out);
+        }
+    }
+
+    impl<T: Json, const N: usize> Json for [T; N] {
+        fn write_json(&self, out: &mut String) {
+            write_seq(self, out);
+        }
+    }
+
+    impl<T: Json> Json for Vec<T> {
+        fn write_json(&self, out: &mut String) {
+            write_seq(self, out);
+        }
+    }
+
+    impl<T: Json> Json for VecDeque<T> {
+        fn write_json(&self, out: &mut String) {
+            write_seq(self, out);
+        }
+    }
+
+    impl<T: Json> Json for BTreeSet<T> {
+        fn write_json(&self, out: &mut String) {
+            write_seq(self, out);
+        }
+    }
+
+    impl<T: Json, S> Json for HashSet<T, S> {
+        fn write_json(&self, out: &mut String) {
+            write_seq(self, out);
+        }
+    }
+
+    impl<K: JsonKey, V: Json> Json for BTreeMap<K, V> {
+        fn write_json(&self, out: &mut String) {
+            write_map(self, out);
+        }
+    }
+
+    impl<K: JsonKey, V: Json, S> Json for HashMap<K, V, S> {
+        fn write_json(&self, out: &mut String) {
+            write_map(self, out);
+        }
+    }
+
+    macro_rules! tuples {
+        ($($t:ident $i:tt),+) => {
+            impl<$($t: Json),+> Json for ($($t,)+) {
+                fn write_json(&self, out: &mut String) {
+                    out.push('[');
+                    $(
+                        if $i > 0 {
+                            out.push(',');
+                        }
+                        self.$i.write_json(out);
+                    )+
+                    out.push(']');
+                }
+            }
+        };
+    }
+
+    tuples!(A 0);
+    tuples!(A 0, B 1);
+    tuples!(A 0, B 1, C 2);
+    tuples!(A 0, B 1, C 2, D 3);
+    tuples!(A 0, B 1, C 2, D 3, E 4);
+    tuples!(A 0, B 1, C 2, D 3, E 4, F 5);
+}
+"##
+}
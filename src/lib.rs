@@ -5,92 +5,236 @@
 
 //! Rust code evaluation
 
+#[cfg(unix)]
+extern crate libc;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate sha2;
 extern crate tempdir;
+extern crate toml;
+
+mod bindings;
+mod cache;
+mod cancel;
+mod cargo;
+mod compiled;
+mod debug_value;
+mod diagnostic;
+mod diff;
+mod evaluator;
+mod format;
+mod header;
+mod json;
+mod limits;
+mod output;
+mod process;
+mod sandbox;
+mod seccomp;
+mod session;
+mod source;
+mod syntax;
+
+pub use crate::bindings::{Bindings, Literal};
+pub use crate::cache::Cache;
+pub use crate::cancel::{CancelToken, EvalHandle};
+pub use crate::cargo::{Cargo, Dependency};
+pub use crate::compiled::{Compiled, RunOptions};
+pub use crate::debug_value::{DebugParseError, DebugValue};
+pub use crate::diagnostic::{Applicability, Diagnostic, DiagnosticCode,
+    DiagnosticSpan, DiagnosticSpanLine, Level};
+pub use crate::diff::{Change, ChangeKind, Diff};
+pub use crate::evaluator::{Edition, Evaluator, EvaluatorBuilder};
+pub use crate::format::OutputFormat;
+pub use crate::limits::{Limit, ResourceLimits};
+pub use crate::output::{EvalOutput, OutputChunk, Stream, Timings};
+pub use crate::sandbox::Sandbox;
+pub use crate::seccomp::SeccompProfile;
+pub use crate::session::Session;
+pub use crate::source::SNIPPET_FILE_NAME;
+pub use crate::syntax::is_complete;
 
 use std::error::Error;
 use std::fmt::{Display, self};
-use std::fs::File;
-use std::io::{self, Write};
-use std::path::Path;
-use std::process::Command;
-use tempdir::TempDir;
+use std::io;
+use std::time::Duration;
 
 /// Type of errors that can occur when calling `eval`.
 #[derive(Debug)]
 pub enum EvalError {
-    /// The string contains the build messages.
-    Build(String),
+    /// The build failed. Contains the diagnostics emitted by the compiler.
+    Build(Vec<Diagnostic>),
     /// Other type of error.
     Other(OtherFailure),
     /// The string contains what was written by the program to stderr.
     ProgReturnedError(String),
+    /// A phase of the evaluation took longer than its configured timeout. The
+    /// processes involved were killed.
+    Timeout {
+        /// Phase that timed out.
+        phase: Phase,
+        /// Time elapsed when the processes were killed.
+        elapsed: Duration,
+    },
+    /// The program was killed or failed because it exceeded one of the
+    /// configured resource limits.
+    ResourceLimitExceeded {
+        /// Limit that was exceeded.
+        limit: Limit,
+        /// What was written by the program to stderr.
+        stderr: String,
+    },
+    /// The sandbox could not be set up, e.g. because unprivileged namespaces
+    /// are not available.
+    SandboxUnavailable(io::Error),
+    /// The program made a system call forbidden by the configured seccomp
+    /// profile.
+    SyscallBlocked {
+        /// Name of the forbidden system call (or its number if the name is
+        /// unknown), if it could be determined.
+        syscall: Option<String>,
+        /// What was written by the program to stderr.
+        stderr: String,
+    },
+    /// The evaluation was cancelled. The processes involved were killed.
+    Cancelled,
+    /// The value of the expression could not be converted to the requested
+    /// type (see `Evaluator::eval_as`).
+    ValueMismatch {
+        /// Value as encoded by the program.
+        value: String,
+        /// Description of the mismatch.
+        message: String,
+    },
 }
 
 impl Error for EvalError {
-    fn cause(&self) -> Option<&Error> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
         match *self {
             EvalError::Other(ref e) => Some(&e.0),
+            EvalError::SandboxUnavailable(ref e) => Some(e),
             _ => None,
         }
     }
-
-    fn description(&self) -> &str {
-        match *self {
-            EvalError::Build(_) => "Build failed",
-            EvalError::Other(_) => "Other error",
-            EvalError::ProgReturnedError(_) => "Program returned an error",
-        }
-    }
 }
 
 impl Display for EvalError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.description())?;
         let s = match *self {
-            EvalError::Build(ref s) => s,
-            EvalError::ProgReturnedError(ref s) => s,
-            _ => return Ok(()),
+            EvalError::Build(ref diagnostics) => {
+                f.write_str("Build failed")?;
+                for diagnostic in diagnostics {
+                    write!(f, "\n{}", diagnostic.to_string().trim_end())?;
+                }
+                return Ok(())
+            }
+            EvalError::Cancelled => return f.write_str("Evaluation cancelled"),
+            EvalError::Other(_) => return f.write_str("Other error"),
+            EvalError::SandboxUnavailable(_) => {
+                return f.write_str("Sandbox unavailable")
+            }
+            EvalError::SyscallBlocked {ref syscall, ref stderr} => {
+                match *syscall {
+                    Some(ref syscall) => {
+                        write!(f, "Forbidden system call: {}", syscall)?
+                    }
+                    None => f.write_str("Forbidden system call")?,
+                }
+                stderr
+            }
+            EvalError::ProgReturnedError(ref s) => {
+                f.write_str("Program returned an error")?;
+                s
+            }
+            EvalError::ValueMismatch {ref value, ref message} => {
+                return write!(f, "Value mismatch: {}\n{}", message, value)
+            }
+            EvalError::Timeout {phase, elapsed} => {
+                return write!(f, "{} timed out after {:?}", phase, elapsed)
+            }
+            EvalError::ResourceLimitExceeded {limit, ref stderr} => {
+                write!(f, "{} exceeded", limit)?;
+                stderr
+            }
         };
         write!(f, "\n{}", s)
     }
 }
 
+/// Phase of an evaluation.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Phase {
+    /// Building the code with rustc.
+    Compile,
+    /// Running the compiled program.
+    Run,
+}
+
+impl Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Phase::Compile => "Compilation",
+            Phase::Run => "Program",
+        })
+    }
+}
+
 /// Other type of errors that can occur when evaluating rust code.
 #[derive(Debug)]
 pub struct OtherFailure(OtherError);
 
 #[derive(Debug)]
 enum OtherError {
+    Cache(io::Error),
+    CopyProgram(io::Error),
     CreateTempDir(io::Error),
+    ReadValue(io::Error),
+    Seccomp(io::Error),
+    SpawnCargo(io::Error),
     SpawnProg(io::Error),
     SpawnRustc(io::Error),
+    Wait(io::Error),
+    WriteManifest(io::Error),
     WriteSrcFile(io::Error),
 }
 
 impl Error for OtherError {
-    fn cause(&self) -> Option<&Error> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
         match *self {
+            OtherError::Cache(ref e) => Some(e),
+            OtherError::CopyProgram(ref e) => Some(e),
             OtherError::CreateTempDir(ref e) => Some(e),
+            OtherError::ReadValue(ref e) => Some(e),
+            OtherError::Seccomp(ref e) => Some(e),
+            OtherError::SpawnCargo(ref e) => Some(e),
             OtherError::SpawnProg(ref e) => Some(e),
             OtherError::SpawnRustc(ref e) => Some(e),
+            OtherError::Wait(ref e) => Some(e),
+            OtherError::WriteManifest(ref e) => Some(e),
             OtherError::WriteSrcFile(ref e) => Some(e),
         }
     }
+}
 
-    fn description(&self) -> &str {
-        match *self {
+impl Display for OtherError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            OtherError::Cache(_) => "Failed to access the cache",
+            OtherError::CopyProgram(_) => "Failed to copy program built by \
+                cargo",
             OtherError::CreateTempDir(_) => "Failed to create temporary \
                 directory",
+            OtherError::ReadValue(_) => "Failed to read the value of the \
+                expression",
+            OtherError::Seccomp(_) => "Failed to set up the seccomp filter",
+            OtherError::SpawnCargo(_) => "Failed to spawn cargo",
             OtherError::SpawnProg(_) => "Failed to spawn program",
             OtherError::SpawnRustc(_) => "Failed to spawn rustc",
+            OtherError::Wait(_) => "Failed to wait for child process",
+            OtherError::WriteManifest(_) => "Failed to write Cargo manifest",
             OtherError::WriteSrcFile(_) => "Failed to write source file",
-        }
-    }
-}
-
-impl Display for OtherError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.description())
+        })
     }
 }
 
@@ -103,53 +247,96 @@ impl From<OtherError> for EvalError {
 /// Evaluates rust code.
 ///
 /// The code is implicitly enclosed in braces to make it an expression.
-/// The type of the expression must be `Debug`. If successful, the result of the
-/// evaluation is formatted with `Debug` and returned.
+/// If successful, the result of the evaluation is formatted with `Debug` and
+/// returned in `EvalOutput::value`, separately from what the program wrote to
+/// stdout and stderr. A result whose type is not `Debug` is replaced by the
+/// name of its type, and `()` by an empty string.
+///
+/// The code can start with a header declaring dependencies and the edition,
+/// either as a frontmatter block or as a `cargo` code block in inner doc
+/// comments:
+///
+/// ```text
+/// ---cargo
+/// [package]
+/// edition = "2021"
+///
+/// [dependencies]
+/// itertools = "0.10"
+/// ---
+/// ```
+///
+/// Dependencies enable the Cargo backend (see `Cargo`). They are only
+/// accepted by evaluators built with `EvaluatorBuilder::header_dependencies`.
+///
+/// This uses the default configuration of `Evaluator`. Use
+/// `Evaluator::builder` to customize how the code is built and run.
 ///
 /// # Limitations
 ///
 /// * Building is delegated to rustc.
 /// * rustc needs to be in the PATH.
 /// * It is slow.
-/// * External crates are not supported.
+/// * External crates require the Cargo backend (see `Cargo`).
 ///
 /// # Examples
 ///
 /// ```rust
 /// use everust::eval;
-/// assert_eq!("2", eval("let n = 1; n + 1").unwrap());
+/// assert_eq!("2", eval("let n = 1; n + 1").unwrap().value);
+///
+/// let output = eval(r#"println!("hi"); 3"#).unwrap();
+/// assert_eq!("3", output.value);
+/// assert_eq!("hi\n", output.stdout);
 /// ```
-pub fn eval(code: &str) -> Result<String, EvalError> {
-    let temp = TempDir::new("").map_err(OtherError::CreateTempDir)?;
-    let code_path = temp.path().join("main.rs");
-    write_source_file(&code_path, code).map_err(OtherError::WriteSrcFile)?;
-    let out_path = temp.path().join("main");
-    let out = Command::new("rustc")
-        .arg("-o")
-        .arg(&out_path)
-        .arg(&code_path)
-        .output()
-        .map_err(OtherError::SpawnRustc)?;
-    if !out.status.success() {
-        return Err(EvalError::Build(String::from_utf8_lossy(&out.stderr)
-            .into_owned()))
-    }
-    let out = Command::new(&out_path).output().map_err(OtherError::SpawnProg)?;
-    if out.status.success() {
-        Ok(String::from_utf8_lossy(&out.stdout).into_owned())
-    } else {
-        Err(EvalError::ProgReturnedError(String::from_utf8_lossy(&out.stderr)
-            .into_owned()))
-    }
+pub fn eval(code: &str) -> Result<EvalOutput, EvalError> {
+    Evaluator::new().eval(code)
+}
+
+/// Evaluates rust code and converts the value of the expression to `T`.
+///
+/// See `Evaluator::eval_as`.
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(3, everust::eval_as::<u8>("1 + 2").unwrap());
+/// ```
+pub fn eval_as<T: serde::de::DeserializeOwned>(code: &str)
+    -> Result<T, EvalError>
+{
+    Evaluator::new().eval_as(code)
 }
 
-fn write_source_file(path: &Path, code: &str) -> io::Result<()> {
-    let mut f = File::create(path)?;
-    write!(&mut f, r##"
-fn main() {{
-    let expr = {{{}}};
-    print!("{{:?}}", expr);
-}}
-    "##, code)?;
-    f.sync_all()
+/// Returns the type of the expression without running the code.
+///
+/// See `Evaluator::type_of`.
+pub fn type_of(code: &str) -> Result<String, EvalError> {
+    Evaluator::new().type_of(code)
+}
+
+/// Evaluates rust code in a background thread.
+///
+/// See `Evaluator::spawn_eval`.
+pub fn spawn_eval(code: &str) -> EvalHandle {
+    Evaluator::new().spawn_eval(code)
+}
+
+/// Async version of `eval`, available with the `tokio` feature.
+///
+/// If the returned future is dropped before completion, the processes
+/// involved in the evaluation are killed. See `Evaluator::eval_async`.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// use everust::eval_async;
+/// assert_eq!("2", eval_async("1 + 1").await.unwrap().value);
+/// # }
+/// ```
+#[cfg(feature = "tokio")]
+pub async fn eval_async(code: &str) -> Result<EvalOutput, EvalError> {
+    Evaluator::new().eval_async(code).await
 }
@@ -0,0 +1,229 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+use std::fmt::{Display, self};
+use std::io;
+use std::process::{Command, ExitStatus};
+use std::time::Duration;
+
+/// Resource limits applied to the evaluated program.
+///
+/// Limits are enforced with `setrlimit` in the program process before it
+/// starts, and are only supported on Linux. A limit set to `None` is inherited
+/// from the current process.
+///
+/// Exceeding the process or open file limit is only detected when the
+/// program panics on the resulting error, e.g. with `unwrap`.
+///
+/// # Examples
+///
+/// ```rust
+/// use everust::{Evaluator, ResourceLimits};
+/// use std::time::Duration;
+/// let limits = ResourceLimits {
+///     memory: Some(512 << 20),
+///     cpu_time: Some(Duration::from_secs(5)),
+///     ..ResourceLimits::default()
+/// };
+/// let evaluator = Evaluator::builder().resource_limits(limits).build();
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ResourceLimits {
+    /// Maximum size of the virtual memory of the program in bytes
+    /// (`RLIMIT_AS`).
+    pub memory: Option<u64>,
+    /// Maximum CPU time of the program (`RLIMIT_CPU`), rounded up to the
+    /// second.
+    pub cpu_time: Option<Duration>,
+    /// Maximum size in bytes of files created by the program
+    /// (`RLIMIT_FSIZE`).
+    pub file_size: Option<u64>,
+    /// Maximum number of processes and threads of the user running the
+    /// program (`RLIMIT_NPROC`).
+    pub processes: Option<u64>,
+    /// Maximum number of file descriptors the program can open
+    /// (`RLIMIT_NOFILE`).
+    pub open_files: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Returns resource limits that inherit everything from the current
+    /// process.
+    pub fn new() -> ResourceLimits {
+        ResourceLimits::default()
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == ResourceLimits::default()
+    }
+}
+
+/// Resource limit that can be exceeded by the evaluated program.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Limit {
+    /// See `ResourceLimits::memory`.
+    Memory,
+    /// See `ResourceLimits::cpu_time`.
+    CpuTime,
+    /// See `ResourceLimits::file_size`.
+    FileSize,
+    /// See `ResourceLimits::processes`.
+    Processes,
+    /// See `ResourceLimits::open_files`.
+    OpenFiles,
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Limit::Memory => "Memory limit",
+            Limit::CpuTime => "CPU time limit",
+            Limit::FileSize => "File size limit",
+            Limit::Processes => "Process count limit",
+            Limit::OpenFiles => "Open file limit",
+        })
+    }
+}
+
+/// Configures the command so that the limits are applied to the spawned
+/// process.
+#[cfg(target_os = "linux")]
+pub fn apply(cmd: &mut Command, limits: &ResourceLimits) -> io::Result<()> {
+    use std::os::unix::process::CommandExt;
+
+    #[cfg(target_env = "gnu")]
+    type Resource = libc::__rlimit_resource_t;
+    #[cfg(not(target_env = "gnu"))]
+    type Resource = libc::c_int;
+
+    fn set(resource: Resource, soft: u64, hard: u64)
+        -> io::Result<()>
+    {
+        let limit = libc::rlimit {rlim_cur: soft, rlim_max: hard};
+        match unsafe { libc::setrlimit(resource, &limit) } {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    if limits.is_empty() {
+        return Ok(())
+    }
+    let limits = *limits;
+    let apply = move || {
+        if let Some(n) = limits.memory {
+            set(libc::RLIMIT_AS, n, n)?;
+        }
+        if let Some(t) = limits.cpu_time {
+            let secs = t.as_secs() + if t.subsec_nanos() > 0 { 1 } else { 0 };
+            // The hard limit is one second above so that SIGXCPU is sent
+            // before SIGKILL.
+            set(libc::RLIMIT_CPU, secs, secs + 1)?;
+        }
+        if let Some(n) = limits.file_size {
+            set(libc::RLIMIT_FSIZE, n, n)?;
+        }
+        if let Some(n) = limits.processes {
+            set(libc::RLIMIT_NPROC, n, n)?;
+        }
+        if let Some(n) = limits.open_files {
+            set(libc::RLIMIT_NOFILE, n, n)?;
+        }
+        Ok(())
+    };
+    unsafe {
+        cmd.pre_exec(apply);
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn apply(_: &mut Command, limits: &ResourceLimits) -> io::Result<()> {
+    if limits.is_empty() {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::Unsupported,
+            "Resource limits are only supported on Linux"))
+    }
+}
+
+/// Determines which limit, if any, caused the program to fail.
+pub fn exceeded(limits: &ResourceLimits, status: &ExitStatus, stderr: &str)
+    -> Option<Limit>
+{
+    if status.success() {
+        return None
+    }
+    let signal = signal(status);
+    if limits.cpu_time.is_some() && signal == Some(SIGXCPU) {
+        Some(Limit::CpuTime)
+    } else if limits.file_size.is_some() && signal == Some(SIGXFSZ) {
+        Some(Limit::FileSize)
+    } else if limits.memory.is_some() && signal == Some(SIGABRT)
+        && stderr.lines().any(|line| {
+            line.starts_with("memory allocation of")
+                && line.ends_with("bytes failed")
+        })
+    {
+        // Reported by the default allocation error handler, which aborts.
+        Some(Limit::Memory)
+    } else if limits.processes.is_some() && panicked(status, stderr, |m| {
+        m.starts_with("failed to spawn thread")
+            && m.contains("kind: WouldBlock")
+    }) {
+        // Reported by the panic of `std::thread::spawn`.
+        Some(Limit::Processes)
+    } else if limits.open_files.is_some() && panicked(status, stderr, |m| {
+        m.ends_with("Too many open files\" }")
+            || m.ends_with("Too many open files (os error 24)")
+    }) {
+        // Reported by a panic on the `Debug` or `Display` form of the error.
+        Some(Limit::OpenFiles)
+    } else {
+        None
+    }
+}
+
+/// Exit code of a program whose main thread panicked.
+const PANIC_EXIT_CODE: i32 = 101;
+
+/// Returns whether the program exited because of a panic whose message
+/// satisfies `matches`.
+///
+/// Only the first line of the messages following the headers written by the
+/// default panic hook is checked.
+fn panicked<F>(status: &ExitStatus, stderr: &str, matches: F) -> bool
+where
+    F: Fn(&str) -> bool,
+{
+    status.code() == Some(PANIC_EXIT_CODE)
+        && stderr.lines().zip(stderr.lines().skip(1))
+            .any(|(header, message)| {
+                header.starts_with("thread '")
+                    && header.contains(" panicked at ")
+                    && matches(message)
+            })
+}
+
+#[cfg(unix)]
+const SIGABRT: i32 = libc::SIGABRT;
+#[cfg(not(unix))]
+const SIGABRT: i32 = -1;
+#[cfg(target_os = "linux")]
+const SIGXCPU: i32 = libc::SIGXCPU;
+#[cfg(target_os = "linux")]
+const SIGXFSZ: i32 = libc::SIGXFSZ;
+#[cfg(not(target_os = "linux"))]
+const SIGXCPU: i32 = -1;
+#[cfg(not(target_os = "linux"))]
+const SIGXFSZ: i32 = -1;
+
+#[cfg(unix)]
+fn signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn signal(_: &ExitStatus) -> Option<i32> {
+    None
+}
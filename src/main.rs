@@ -0,0 +1,181 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![deny(warnings)]
+
+//! Interactive Rust REPL
+
+extern crate everust;
+extern crate rustyline;
+
+use everust::{EvalError, Evaluator, OutputFormat, Session};
+use rustyline::DefaultEditor;
+use rustyline::error::ReadlineError;
+use std::env;
+use std::mem;
+use std::path::PathBuf;
+use std::process;
+
+const HELP: &str = "\
+Enter Rust statements, items or expressions. Input continues on the next line
+until delimiters are balanced. Bindings and items are kept across inputs.
+
+Commands:
+  :help     Show this help
+  :history  Show the code of the session
+  :type     Show the type of an expression without running it
+  :format   Set how values are printed: debug, pretty, display, hex, binary
+            or a format string such as {:>8.3}
+  :reset    Forget all bindings and items
+  :quit     Exit (also Ctrl-D)";
+
+fn main() {
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("everust: {}", e);
+            process::exit(1)
+        }
+    };
+    let history_path = history_path();
+    if let Some(ref path) = history_path {
+        // The history file does not exist on first use.
+        let _ = editor.load_history(path);
+    }
+    let evaluator = Evaluator::builder().header_dependencies(true).build();
+    let mut session = Session::with_evaluator(evaluator);
+    let mut input = String::new();
+    loop {
+        let prompt = if input.is_empty() { ">> " } else { ".. " };
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                input.clear();
+                continue
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("everust: {}", e);
+                break
+            }
+        };
+        if input.is_empty() && line.trim_start().starts_with(':') {
+            let _ = editor.add_history_entry(line.trim());
+            if !run_command(&mut session, line.trim()) {
+                break
+            }
+            continue
+        }
+        input.push_str(&line);
+        input.push('\n');
+        if !everust::is_complete(&input) {
+            continue
+        }
+        let code = mem::take(&mut input);
+        if code.trim().is_empty() {
+            continue
+        }
+        let _ = editor.add_history_entry(code.trim_end());
+        eval(&mut session, &code);
+    }
+    if let Some(ref path) = history_path {
+        if let Err(e) = editor.save_history(path) {
+            eprintln!("everust: Failed to save history: {}", e);
+        }
+    }
+}
+
+/// Returns the path of the history file, from `EVERUST_HISTORY` or in the
+/// home directory.
+fn history_path() -> Option<PathBuf> {
+    env::var_os("EVERUST_HISTORY")
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME")
+                .map(|home| PathBuf::from(home).join(".everust_history"))
+        })
+}
+
+/// Runs a REPL command. Returns `false` to exit.
+fn run_command(session: &mut Session, command: &str) -> bool {
+    match command {
+        ":help" | ":h" => println!("{}", HELP),
+        ":history" => {
+            for code in session.history() {
+                println!("{}", code.trim_end());
+            }
+        }
+        ":reset" => session.clear(),
+        _ if command.starts_with(":type ") => {
+            match session.type_of(&command[":type ".len()..]) {
+                Ok(ty) => println!("{}", ty),
+                Err(e) => print_error(e),
+            }
+        }
+        _ if command.starts_with(":format ") => {
+            match parse_format(command[":format ".len()..].trim()) {
+                Some(format) => set_format(session, format),
+                None => eprintln!("Unknown format. Type :help for help."),
+            }
+        }
+        ":quit" | ":q" => return false,
+        _ => eprintln!("Unknown command {}. Type :help for help.", command),
+    }
+    true
+}
+
+fn parse_format(s: &str) -> Option<OutputFormat> {
+    match s {
+        "debug" => Some(OutputFormat::Debug),
+        "pretty" => Some(OutputFormat::PrettyDebug),
+        "display" => Some(OutputFormat::Display),
+        "hex" => Some(OutputFormat::Hex),
+        "binary" => Some(OutputFormat::Binary),
+        _ if s.contains('{') => Some(OutputFormat::Custom(s.to_owned())),
+        _ => None,
+    }
+}
+
+/// Sets the output format of the session, once a custom format string is
+/// known to compile with a single argument.
+fn set_format(session: &mut Session, format: OutputFormat) {
+    if let OutputFormat::Custom(ref s) = format {
+        let check = format!("format!({:?}, 0)", s);
+        match session.evaluator().type_of(&check) {
+            Ok(_) => {}
+            // Only the first error is about the format string.
+            Err(EvalError::Build(mut diagnostics)) => {
+                diagnostics.truncate(1);
+                return print_error(EvalError::Build(diagnostics))
+            }
+            Err(e) => return print_error(e),
+        }
+    }
+    session.set_output_format(format);
+}
+
+fn eval(session: &mut Session, code: &str) {
+    match session.eval(code) {
+        Ok(output) => {
+            print!("{}", output.stdout);
+            eprint!("{}", output.stderr);
+            if !output.stdout.is_empty() && !output.stdout.ends_with('\n') {
+                println!();
+            }
+            if !output.value.is_empty() {
+                println!("{}", output.value);
+            }
+        }
+        Err(e) => print_error(e),
+    }
+}
+
+fn print_error(e: EvalError) {
+    match e {
+        EvalError::Build(diagnostics) => {
+            for diagnostic in &diagnostics {
+                eprintln!("{}", diagnostic.to_string().trim_end());
+            }
+        }
+        e => eprintln!("{}", e),
+    }
+}
@@ -0,0 +1,70 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+use crate::debug_value::{DebugParseError, DebugValue};
+use std::process::ExitStatus;
+use std::time::Duration;
+
+/// Result of a successful evaluation.
+#[derive(Clone, Debug)]
+pub struct EvalOutput {
+    /// Value of the evaluated expression, formatted as set by
+    /// `EvaluatorBuilder::output_format` (with `Debug` by default).
+    ///
+    /// This is empty if the value is `()`, or if the program exited
+    /// successfully before the value was computed (e.g. by calling
+    /// `std::process::exit(0)`).
+    pub value: String,
+    /// Name of the type of the evaluated expression, as returned by
+    /// `std::any::type_name`, e.g. `alloc::vec::Vec<i32>`.
+    ///
+    /// Like `value`, this is empty if the program exited successfully before
+    /// the value was computed.
+    pub type_name: String,
+    /// What was written by the program to stdout.
+    pub stdout: String,
+    /// What was written by the program to stderr.
+    pub stderr: String,
+    /// Exit status of the program.
+    pub status: ExitStatus,
+    /// Durations of the evaluation phases.
+    pub timings: Timings,
+    /// Whether the program was found in the cache instead of being built.
+    pub cached: bool,
+}
+
+impl EvalOutput {
+    /// Parses `value` into a tree. See `DebugValue`.
+    pub fn parse_value(&self) -> Result<DebugValue, DebugParseError> {
+        DebugValue::parse(&self.value)
+    }
+}
+
+/// Durations of the evaluation phases.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Timings {
+    /// Time spent building the program.
+    pub compile: Duration,
+    /// Time spent running the program.
+    pub run: Duration,
+}
+
+/// Piece of output written by the program, as passed to the callback of
+/// `Compiled::run_streaming`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OutputChunk {
+    /// Stream the data was written to.
+    pub stream: Stream,
+    /// Data as written by the program.
+    pub data: Vec<u8>,
+    /// Time elapsed since the program started when the data was read.
+    pub time: Duration,
+}
+
+/// Output stream of the program.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Stream {
+    /// Standard output.
+    Stdout,
+    /// Standard error.
+    Stderr,
+}
@@ -0,0 +1,353 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+//! Spawning and supervision of child processes.
+
+use crate::output::{OutputChunk, Stream};
+use std::io::{self, Read, Write};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Result of running a child process to completion.
+#[derive(Debug)]
+pub struct Finished {
+    pub status: ExitStatus,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum Outcome {
+    Finished(Finished),
+    TimedOut(Duration),
+    Cancelled,
+}
+
+/// Error that can occur when running a child process.
+#[derive(Debug)]
+pub enum RunError {
+    Spawn(io::Error),
+    Wait(io::Error),
+}
+
+/// Runs a command, killing it and all the processes in its process group if it
+/// runs for longer than `timeout`.
+///
+/// `stdin` is written to the standard input of the child, which is empty if
+/// `stdin` is `None`. The processes are also killed when `cancel` is set.
+/// What the child writes is passed to `on_output` as it is read, on the
+/// calling thread.
+pub fn run(mut cmd: Command, stdin: Option<Vec<u8>>,
+    timeout: Option<Duration>, cancel: Option<&AtomicBool>,
+    mut on_output: Option<&mut dyn FnMut(OutputChunk)>)
+    -> Result<Outcome, RunError>
+{
+    let is_cancelled = || cancel.is_some_and(|c| c.load(Ordering::SeqCst));
+    if is_cancelled() {
+        return Ok(Outcome::Cancelled)
+    }
+    configure(&mut cmd, stdin.is_some());
+    let start = Instant::now();
+    let mut child = cmd.spawn().map_err(RunError::Spawn)?;
+    let writer = match (child.stdin.take(), stdin) {
+        (Some(mut pipe), Some(data)) => Some(thread::spawn(move || {
+            // The child may exit without reading all its input.
+            let _ = pipe.write_all(&data);
+        })),
+        _ => None,
+    };
+    let (sender, chunks) = match on_output {
+        Some(_) => {
+            let (sender, receiver) = mpsc::channel();
+            (Some(sender), Some(receiver))
+        }
+        None => (None, None),
+    };
+    let stdout = child.stdout.take().map(|pipe| {
+        read_in_background(pipe, Stream::Stdout, start, sender.clone())
+    });
+    let stderr = child.stderr.take().map(|pipe| {
+        read_in_background(pipe, Stream::Stderr, start, sender.clone())
+    });
+    drop(sender);
+    let mut wait = |interval: Duration| match (&chunks, &mut on_output) {
+        (Some(chunks), Some(on_output)) => {
+            match chunks.recv_timeout(interval) {
+                Ok(chunk) => {
+                    on_output(chunk);
+                    chunks.try_iter().for_each(&mut *on_output);
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => thread::sleep(interval),
+            }
+        }
+        _ => thread::sleep(interval),
+    };
+    let mut timed_out = false;
+    let mut cancelled = false;
+    loop {
+        if has_exited(&mut child).map_err(RunError::Wait)? {
+            break
+        }
+        if is_cancelled() {
+            cancelled = true;
+            break
+        }
+        let elapsed = start.elapsed();
+        match timeout {
+            Some(timeout) if elapsed >= timeout => {
+                timed_out = true;
+                break
+            }
+            Some(timeout) => wait(POLL_INTERVAL.min(timeout - elapsed)),
+            None => wait(POLL_INTERVAL),
+        }
+    }
+    // Processes left behind by the child are killed as well so that they do
+    // not keep the pipes open.
+    kill(&mut child);
+    let status = child.wait().map_err(RunError::Wait)?;
+    let elapsed = start.elapsed();
+    let stdout = join_reader(stdout).map_err(RunError::Wait)?;
+    let stderr = join_reader(stderr).map_err(RunError::Wait)?;
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+    if let (Some(chunks), Some(on_output)) = (chunks, on_output) {
+        chunks.try_iter().for_each(on_output);
+    }
+    if cancelled {
+        return Ok(Outcome::Cancelled)
+    }
+    if timed_out {
+        return Ok(Outcome::TimedOut(elapsed))
+    }
+    Ok(Outcome::Finished(Finished {status, stdout, stderr}))
+}
+
+/// Async version of `run`.
+///
+/// If the returned future is dropped before completion, the child and all the
+/// processes in its process group are killed.
+#[cfg(feature = "tokio")]
+pub async fn run_async(mut cmd: Command, stdin: Option<Vec<u8>>,
+    timeout: Option<Duration>) -> Result<Outcome, RunError>
+{
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    configure(&mut cmd, stdin.is_some());
+    let mut cmd = tokio::process::Command::from(cmd);
+    cmd.kill_on_drop(true);
+    let start = Instant::now();
+    let mut child = cmd.spawn().map_err(RunError::Spawn)?;
+    let pid = child.id().ok_or_else(|| {
+        RunError::Wait(io::Error::other("Child exited before being waited"))
+    })?;
+    let mut guard = KillGuard(Some(pid));
+    let stdin_pipe = child.stdin.take();
+    let write = async {
+        if let (Some(mut pipe), Some(data)) = (stdin_pipe, stdin) {
+            // The child may exit without reading all its input.
+            let _ = pipe.write_all(&data).await;
+        }
+    };
+    let mut stdout_pipe = child.stdout.take();
+    let read_stdout = async {
+        let mut buf = Vec::new();
+        if let Some(ref mut pipe) = stdout_pipe {
+            pipe.read_to_end(&mut buf).await?;
+        }
+        Ok::<_, io::Error>(buf)
+    };
+    let mut stderr_pipe = child.stderr.take();
+    let read_stderr = async {
+        let mut buf = Vec::new();
+        if let Some(ref mut pipe) = stderr_pipe {
+            pipe.read_to_end(&mut buf).await?;
+        }
+        Ok::<_, io::Error>(buf)
+    };
+    let supervise = async {
+        let timed_out = loop {
+            match has_exited_async(pid, &mut child) {
+                Ok(true) => break Ok(false),
+                Ok(false) => {}
+                Err(e) => break Err(e),
+            }
+            let elapsed = start.elapsed();
+            let interval = match timeout {
+                Some(timeout) if elapsed >= timeout => break Ok(true),
+                Some(timeout) => POLL_INTERVAL.min(timeout - elapsed),
+                None => POLL_INTERVAL,
+            };
+            tokio::time::sleep(interval).await;
+        };
+        // Processes left behind by the child are killed as well so that they
+        // do not keep the pipes open.
+        kill_group(pid, &mut child);
+        timed_out
+    };
+    let ((), stdout, stderr, timed_out) =
+        tokio::join!(write, read_stdout, read_stderr, supervise);
+    // The process group must not be killed once the child is reaped, as its
+    // id may be reused.
+    guard.0 = None;
+    let status = child.wait().await.map_err(RunError::Wait)?;
+    let elapsed = start.elapsed();
+    let timed_out = timed_out.map_err(RunError::Wait)?;
+    let stdout = stdout.map_err(RunError::Wait)?;
+    let stderr = stderr.map_err(RunError::Wait)?;
+    if timed_out {
+        return Ok(Outcome::TimedOut(elapsed))
+    }
+    Ok(Outcome::Finished(Finished {status, stdout, stderr}))
+}
+
+/// Kills the process group of a child when dropped, unless disarmed.
+#[cfg(feature = "tokio")]
+struct KillGuard(Option<u32>);
+
+#[cfg(feature = "tokio")]
+impl Drop for KillGuard {
+    fn drop(&mut self) {
+        // On other platforms, the child is killed by `kill_on_drop`.
+        #[cfg(unix)]
+        if let Some(pid) = self.0 {
+            unsafe {
+                libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "tokio", unix))]
+fn has_exited_async(pid: u32, _: &mut tokio::process::Child)
+    -> io::Result<bool>
+{
+    has_exited_pid(pid)
+}
+
+#[cfg(all(feature = "tokio", not(unix)))]
+fn has_exited_async(_: u32, child: &mut tokio::process::Child)
+    -> io::Result<bool>
+{
+    child.try_wait().map(|status| status.is_some())
+}
+
+#[cfg(all(feature = "tokio", unix))]
+fn kill_group(pid: u32, _: &mut tokio::process::Child) {
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+#[cfg(all(feature = "tokio", not(unix)))]
+fn kill_group(_: u32, child: &mut tokio::process::Child) {
+    let _ = child.start_kill();
+}
+
+/// Sets up the standard streams and process group of a child.
+fn configure(cmd: &mut Command, has_stdin: bool) {
+    let stdin = if has_stdin { Stdio::piped() } else { Stdio::null() };
+    cmd.stdin(stdin).stdout(Stdio::piped()).stderr(Stdio::piped());
+    set_process_group(cmd);
+}
+
+/// Reads everything from `reader` in a new thread, sending the data to
+/// `sender` as it is read.
+fn read_in_background<R>(mut reader: R, stream: Stream, start: Instant,
+    sender: Option<Sender<OutputChunk>>) -> JoinHandle<io::Result<Vec<u8>>>
+where
+    R: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        let sender = match sender {
+            Some(sender) => sender,
+            None => {
+                let mut buf = Vec::new();
+                reader.read_to_end(&mut buf)?;
+                return Ok(buf)
+            }
+        };
+        let mut buf = Vec::new();
+        let mut chunk = [0; 8192];
+        loop {
+            let n = match reader.read(&mut chunk) {
+                Ok(0) => return Ok(buf),
+                Ok(n) => n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {
+                    continue
+                }
+                Err(e) => return Err(e),
+            };
+            buf.extend_from_slice(&chunk[..n]);
+            // The receiver is gone if supervision failed.
+            let _ = sender.send(OutputChunk {
+                stream,
+                data: chunk[..n].to_vec(),
+                time: start.elapsed(),
+            });
+        }
+    })
+}
+
+fn join_reader(reader: Option<JoinHandle<io::Result<Vec<u8>>>>)
+    -> io::Result<Vec<u8>>
+{
+    match reader {
+        Some(reader) => reader.join().unwrap_or_else(|_| {
+            Err(io::Error::other("Reader thread panicked"))
+        }),
+        None => Ok(Vec::new()),
+    }
+}
+
+#[cfg(unix)]
+fn set_process_group(cmd: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    cmd.process_group(0);
+}
+
+#[cfg(not(unix))]
+fn set_process_group(_: &mut Command) {}
+
+/// Checks if the child has exited without reaping it, so that its process
+/// group can still be safely killed.
+#[cfg(unix)]
+fn has_exited(child: &mut Child) -> io::Result<bool> {
+    has_exited_pid(child.id())
+}
+
+#[cfg(unix)]
+fn has_exited_pid(pid: u32) -> io::Result<bool> {
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    let r = unsafe {
+        libc::waitid(libc::P_PID, pid as libc::id_t, &mut info,
+            libc::WEXITED | libc::WNOHANG | libc::WNOWAIT)
+    };
+    if r < 0 {
+        return Err(io::Error::last_os_error())
+    }
+    Ok(unsafe { info.si_pid() } != 0)
+}
+
+#[cfg(not(unix))]
+fn has_exited(child: &mut Child) -> io::Result<bool> {
+    child.try_wait().map(|status| status.is_some())
+}
+
+#[cfg(unix)]
+fn kill(child: &mut Child) {
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill(child: &mut Child) {
+    let _ = child.kill();
+}
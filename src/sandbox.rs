@@ -0,0 +1,494 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Directory where the evaluation directory is mounted in the sandbox.
+pub const WORK_DIR: &str = "/tmp";
+
+/// Configuration of the sandbox in which the evaluated program runs.
+///
+/// The sandbox relies on unprivileged Linux namespaces. The program runs in
+/// new user, mount, network, PID, IPC and UTS namespaces. Its root is a
+/// read-only minimal filesystem made of:
+///
+/// * The read-only paths (by default `/bin`, `/lib`, `/lib32`, `/lib64` and
+///   `/usr`, which are needed to run dynamically linked programs).
+/// * `/dev/null`, `/dev/zero`, `/dev/full`, `/dev/random` and `/dev/urandom`.
+/// * `/proc` for the new PID namespace, when the host allows mounting it.
+/// * `/tmp`, the writable temporary directory of the evaluation, which is also
+///   the working directory of the program.
+///
+/// The program has no network access besides a loopback interface that is
+/// down.
+///
+/// Sandboxing is only supported on Linux. When namespaces are unavailable,
+/// evaluation fails with `EvalError::SandboxUnavailable`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use everust::{Evaluator, Sandbox};
+/// let evaluator = Evaluator::builder().sandbox(Sandbox::new()).build();
+/// let r = evaluator.eval(r#"std::fs::read_to_string("/etc/passwd").is_ok()"#);
+/// assert_eq!("false", r.unwrap().value);
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Sandbox {
+    read_only_paths: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    /// Returns the default sandbox configuration.
+    pub fn new() -> Sandbox {
+        let paths = ["/bin", "/lib", "/lib32", "/lib64", "/usr"];
+        Sandbox {
+            read_only_paths: paths.iter().map(PathBuf::from).collect(),
+        }
+    }
+
+    /// Makes an absolute host path visible read-only at the same location in
+    /// the sandbox.
+    ///
+    /// Paths that do not exist on the host are ignored.
+    pub fn read_only_path<P: Into<PathBuf>>(mut self, path: P) -> Sandbox {
+        self.read_only_paths.push(path.into());
+        self
+    }
+}
+
+impl Default for Sandbox {
+    fn default() -> Sandbox {
+        Sandbox::new()
+    }
+}
+
+/// Configures the command to run in the sandbox.
+///
+/// `work_dir` is mounted at `WORK_DIR` in the sandbox. The command program
+/// must be given as a path in the sandbox.
+#[cfg(target_os = "linux")]
+pub fn apply(cmd: &mut Command, sandbox: &Sandbox, work_dir: &Path)
+    -> io::Result<()>
+{
+    use std::os::unix::process::CommandExt;
+
+    let plan = linux::Plan::new(sandbox, work_dir)?;
+    unsafe {
+        cmd.pre_exec(move || plan.enter());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn apply(_: &mut Command, _: &Sandbox, _: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+        "Sandboxing is only supported on Linux"))
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use libc::{c_int, c_ulong};
+    use std::ffi::{CStr, CString, OsStr};
+    use std::fs;
+    use std::io;
+    use std::mem;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Component, Path, PathBuf};
+    use std::ptr;
+    use super::{Sandbox, WORK_DIR};
+    use tempdir::TempDir;
+
+    const DEVICES: &[&str] = &["/dev/full", "/dev/null", "/dev/random",
+        "/dev/urandom", "/dev/zero"];
+    const OLD_ROOT: &str = ".old-root";
+
+    macro_rules! cstr {
+        ($s:expr) => {
+            unsafe { CStr::from_bytes_with_nul_unchecked(concat!($s, "\0")
+                .as_bytes()) }
+        };
+    }
+
+    enum Step {
+        Mkdir(CString),
+        Symlink {target: CString, link: CString},
+        Bind {
+            src: CString,
+            dst: CString,
+            file: bool,
+            read_only: Option<c_ulong>,
+        },
+    }
+
+    /// Everything needed to enter the sandbox, prepared before forking as
+    /// only async-signal-safe operations are allowed in the child.
+    pub struct Plan {
+        // Mount point of the new root. It must outlive the child process.
+        _root_dir: TempDir,
+        root: CString,
+        steps: Vec<Step>,
+        old_root: CString,
+        work_dir: CString,
+        uid_map: Vec<u8>,
+        gid_map: Vec<u8>,
+    }
+
+    impl Plan {
+        pub fn new(sandbox: &Sandbox, work_dir: &Path) -> io::Result<Plan> {
+            let root_dir = TempDir::new("everust-root")?;
+            let root = root_dir.path().to_owned();
+            let mut steps = Vec::new();
+            let mut dirs = Vec::new();
+            for path in &sandbox.read_only_paths {
+                let meta = match fs::symlink_metadata(path) {
+                    Ok(meta) => meta,
+                    Err(_) => continue,
+                };
+                let dst = sandbox_path(&root, path)?;
+                add_parent_dirs(&mut steps, &mut dirs, &root, &dst)?;
+                if meta.file_type().is_symlink() {
+                    steps.push(Step::Symlink {
+                        target: cstring(fs::read_link(path)?.as_os_str())?,
+                        link: cstring(dst.as_os_str())?,
+                    });
+                } else {
+                    if meta.is_dir() {
+                        add_dir(&mut steps, &mut dirs, dst.clone())?;
+                    }
+                    steps.push(Step::Bind {
+                        src: cstring(path.as_os_str())?,
+                        dst: cstring(dst.as_os_str())?,
+                        file: !meta.is_dir(),
+                        read_only: Some(mount_flags(path)?),
+                    });
+                }
+            }
+            for device in DEVICES {
+                let device = Path::new(device);
+                if !device.exists() {
+                    continue
+                }
+                let dst = sandbox_path(&root, device)?;
+                add_parent_dirs(&mut steps, &mut dirs, &root, &dst)?;
+                steps.push(Step::Bind {
+                    src: cstring(device.as_os_str())?,
+                    dst: cstring(dst.as_os_str())?,
+                    file: true,
+                    read_only: None,
+                });
+            }
+            for dir in &["/proc", WORK_DIR] {
+                add_dir(&mut steps, &mut dirs, root.join(&dir[1..]))?;
+            }
+            add_dir(&mut steps, &mut dirs, root.join(OLD_ROOT))?;
+            steps.push(Step::Bind {
+                src: cstring(work_dir.as_os_str())?,
+                dst: cstring(root.join(&WORK_DIR[1..]).as_os_str())?,
+                file: false,
+                read_only: None,
+            });
+            let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+            Ok(Plan {
+                root: cstring(root.as_os_str())?,
+                _root_dir: root_dir,
+                steps,
+                old_root: cstring(OsStr::new(OLD_ROOT))?,
+                work_dir: cstring(OsStr::new(WORK_DIR))?,
+                uid_map: format!("{} {} 1", uid, uid).into_bytes(),
+                gid_map: format!("{} {} 1", gid, gid).into_bytes(),
+            })
+        }
+
+        /// Enters the sandbox. Runs in the child process after fork.
+        ///
+        /// The namespaces and mounts are set up in the forked process, which
+        /// then forks the init process of the new PID namespace, which in turn
+        /// forks the process that returns from this function and executes the
+        /// program. The first process waits for the program and exits with
+        /// the same status, so that signals like `SIGXCPU` are reported
+        /// faithfully (they would be ignored by an init process).
+        pub fn enter(&self) -> io::Result<()> {
+            check(unsafe {
+                libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNS
+                    | libc::CLONE_NEWNET | libc::CLONE_NEWPID
+                    | libc::CLONE_NEWIPC | libc::CLONE_NEWUTS)
+            })?;
+            write_file(cstr!("/proc/self/setgroups"), b"deny")?;
+            write_file(cstr!("/proc/self/uid_map"), &self.uid_map)?;
+            write_file(cstr!("/proc/self/gid_map"), &self.gid_map)?;
+            mount(None, cstr!("/"), None, libc::MS_REC | libc::MS_PRIVATE)?;
+            mount(Some(cstr!("tmpfs")), &self.root, Some(cstr!("tmpfs")),
+                libc::MS_NOSUID | libc::MS_NODEV)?;
+            for step in &self.steps {
+                self.run_step(step)?;
+            }
+            check(unsafe { libc::chdir(self.root.as_ptr()) })?;
+            let new_root = cstr!(".");
+            check(unsafe {
+                libc::syscall(libc::SYS_pivot_root, new_root.as_ptr(),
+                    self.old_root.as_ptr()) as c_int
+            })?;
+            let mut fds = [0; 2];
+            check(unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) })?;
+            let (reader, writer) = (fds[0], fds[1]);
+            match check(unsafe { libc::fork() })? {
+                0 => {
+                    unsafe { libc::close(reader); }
+                    self.init(writer)
+                }
+                init_pid => {
+                    unsafe { libc::close(writer); }
+                    supervise(init_pid, reader)
+                }
+            }
+        }
+
+        fn run_step(&self, step: &Step) -> io::Result<()> {
+            match *step {
+                Step::Mkdir(ref path) => {
+                    check(unsafe { libc::mkdir(path.as_ptr(), 0o755) })?;
+                    Ok(())
+                }
+                Step::Symlink {ref target, ref link} => {
+                    check(unsafe {
+                        libc::symlink(target.as_ptr(), link.as_ptr())
+                    })?;
+                    Ok(())
+                }
+                Step::Bind {ref src, ref dst, file, read_only} => {
+                    if file {
+                        let fd = check(unsafe {
+                            libc::open(dst.as_ptr(), libc::O_CREAT
+                                | libc::O_WRONLY | libc::O_CLOEXEC, 0o644)
+                        })?;
+                        unsafe { libc::close(fd); }
+                    }
+                    mount(Some(src.as_c_str()), dst, None,
+                        libc::MS_BIND | libc::MS_REC)?;
+                    match read_only {
+                        Some(flags) => mount(None, dst, None, libc::MS_REMOUNT
+                            | libc::MS_BIND | libc::MS_RDONLY | flags),
+                        None => Ok(()),
+                    }
+                }
+            }
+        }
+
+        /// Runs as the init process of the new PID namespace.
+        fn init(&self, writer: c_int) -> io::Result<()> {
+            let r = self.setup_new_root()
+                .and_then(|_| check(unsafe { libc::fork() }));
+            let program = match r {
+                Ok(0) => {
+                    unsafe { libc::close(writer); }
+                    return Ok(())
+                }
+                Ok(pid) => pid,
+                Err(e) => {
+                    let errno = e.raw_os_error().unwrap_or(libc::EIO);
+                    write_message(writer, 1, errno);
+                    unsafe { libc::_exit(1) }
+                }
+            };
+            close_fds_except(writer);
+            write_message(writer, 0, 0);
+            let mut status = 0;
+            loop {
+                let pid = unsafe { libc::waitpid(-1, &mut status, 0) };
+                if pid == program || pid < 0 {
+                    break
+                }
+            }
+            write_message(writer, 0, status);
+            // Exiting kills the other processes in the namespace.
+            unsafe { libc::_exit(0) }
+        }
+
+        fn setup_new_root(&self) -> io::Result<()> {
+            let root = cstr!("/");
+            check(unsafe { libc::chdir(root.as_ptr()) })?;
+            // Mounting /proc is not allowed when the host procfs is
+            // partially masked (e.g. in some containers), in which case it is
+            // not provided.
+            let proc_fs = cstr!("proc");
+            let _ = mount(Some(proc_fs), cstr!("/proc"), Some(proc_fs),
+                libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC);
+            check(unsafe {
+                libc::umount2(self.old_root.as_ptr(), libc::MNT_DETACH)
+            })?;
+            check(unsafe { libc::rmdir(self.old_root.as_ptr()) })?;
+            mount(None, cstr!("/"), None, libc::MS_REMOUNT | libc::MS_BIND
+                | libc::MS_RDONLY | libc::MS_NOSUID | libc::MS_NODEV)?;
+            check(unsafe { libc::chdir(self.work_dir.as_ptr()) })?;
+            Ok(())
+        }
+    }
+
+
+    /// Waits for the init process to report the status of the program and
+    /// exits accordingly.
+    fn supervise(init_pid: c_int, reader: c_int) -> io::Result<()> {
+        let message = read_message(reader);
+        let mut init_status = 0;
+        match message {
+            Some((0, _)) => {}
+            Some((_, errno)) => {
+                unsafe { libc::waitpid(init_pid, &mut init_status, 0); }
+                return Err(io::Error::from_raw_os_error(errno))
+            }
+            None => {
+                unsafe { libc::waitpid(init_pid, &mut init_status, 0); }
+                return Err(io::Error::from_raw_os_error(libc::EIO))
+            }
+        }
+        // Closes the pipe used by the parent to detect a successful exec, so
+        // that it stops waiting for this process.
+        close_fds_except(reader);
+        let status = read_message(reader).map(|(_, status)| status);
+        unsafe { libc::waitpid(init_pid, &mut init_status, 0); }
+        exit_like(status.unwrap_or(init_status))
+    }
+
+    fn exit_like(status: c_int) -> ! {
+        unsafe {
+            if libc::WIFSIGNALED(status) {
+                let signal = libc::WTERMSIG(status);
+                libc::signal(signal, libc::SIG_DFL);
+                let mut set = mem::zeroed();
+                libc::sigemptyset(&mut set);
+                libc::sigaddset(&mut set, signal);
+                libc::sigprocmask(libc::SIG_UNBLOCK, &set, ptr::null_mut());
+                libc::kill(libc::getpid(), signal);
+                libc::_exit(128 + signal)
+            } else {
+                libc::_exit(libc::WEXITSTATUS(status))
+            }
+        }
+    }
+
+    fn write_message(fd: c_int, tag: c_int, value: c_int) {
+        let message = [tag, value];
+        unsafe {
+            libc::write(fd, message.as_ptr() as *const _,
+                mem::size_of_val(&message));
+        }
+    }
+
+    fn read_message(fd: c_int) -> Option<(c_int, c_int)> {
+        let mut message = [0 as c_int; 2];
+        let size = mem::size_of_val(&message);
+        let n = unsafe {
+            libc::read(fd, message.as_mut_ptr() as *mut _, size)
+        };
+        if n == size as isize { Some((message[0], message[1])) } else { None }
+    }
+
+    fn close_fds_except(fd: c_int) {
+        for (first, last) in [(3, fd - 1), (fd + 1, c_int::MAX)] {
+            if first > last {
+                continue
+            }
+            let r = unsafe {
+                libc::syscall(libc::SYS_close_range, first as libc::c_uint,
+                    last as libc::c_uint, 0)
+            };
+            if r < 0 {
+                for other in first..last.min(4096) {
+                    unsafe { libc::close(other); }
+                }
+            }
+        }
+    }
+
+    fn mount(src: Option<&CStr>, dst: &CStr, fs: Option<&CStr>,
+        flags: c_ulong) -> io::Result<()>
+    {
+        let ptr = |s: Option<&CStr>| s.map_or(ptr::null(), CStr::as_ptr);
+        check(unsafe {
+            libc::mount(ptr(src), dst.as_ptr(), ptr(fs), flags, ptr::null())
+        })?;
+        Ok(())
+    }
+
+    fn write_file(path: &CStr, content: &[u8]) -> io::Result<()> {
+        let fd = check(unsafe {
+            libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC)
+        })?;
+        let n = unsafe {
+            libc::write(fd, content.as_ptr() as *const _, content.len())
+        };
+        let r = if n < 0 { Err(io::Error::last_os_error()) } else { Ok(()) };
+        unsafe { libc::close(fd); }
+        r
+    }
+
+    fn check(r: c_int) -> io::Result<c_int> {
+        if r < 0 { Err(io::Error::last_os_error()) } else { Ok(r) }
+    }
+
+    fn cstring(s: &OsStr) -> io::Result<CString> {
+        CString::new(s.as_bytes()).map_err(io::Error::other)
+    }
+
+    /// Returns the location of a host path in the new root.
+    fn sandbox_path(root: &Path, path: &Path) -> io::Result<PathBuf> {
+        let mut dst = root.to_owned();
+        for component in path.components() {
+            match component {
+                Component::RootDir => {}
+                Component::Normal(c) => dst.push(c),
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                    format!("Invalid sandbox path: {}", path.display()))),
+            }
+        }
+        if !path.is_absolute() || dst == root {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("Invalid sandbox path: {}", path.display())))
+        }
+        Ok(dst)
+    }
+
+    fn add_parent_dirs(steps: &mut Vec<Step>, dirs: &mut Vec<PathBuf>,
+        root: &Path, path: &Path) -> io::Result<()>
+    {
+        let parents = path.ancestors()
+            .skip(1)
+            .take_while(|&p| p != root)
+            .collect::<Vec<_>>();
+        for dir in parents.into_iter().rev() {
+            add_dir(steps, dirs, dir.to_owned())?;
+        }
+        Ok(())
+    }
+
+    fn add_dir(steps: &mut Vec<Step>, dirs: &mut Vec<PathBuf>, dir: PathBuf)
+        -> io::Result<()>
+    {
+        if !dirs.contains(&dir) {
+            steps.push(Step::Mkdir(cstring(dir.as_os_str())?));
+            dirs.push(dir);
+        }
+        Ok(())
+    }
+
+    /// Returns the flags of the mount containing `path` that must be kept
+    /// when remounting it in a user namespace.
+    fn mount_flags(path: &Path) -> io::Result<c_ulong> {
+        let path = cstring(path.as_os_str())?;
+        let mut stat: libc::statvfs = unsafe { mem::zeroed() };
+        check(unsafe { libc::statvfs(path.as_ptr(), &mut stat) })?;
+        let mapping = [
+            (libc::ST_NOSUID, libc::MS_NOSUID),
+            (libc::ST_NODEV, libc::MS_NODEV),
+            (libc::ST_NOEXEC, libc::MS_NOEXEC),
+            (libc::ST_NOATIME, libc::MS_NOATIME),
+            (libc::ST_NODIRATIME, libc::MS_NODIRATIME),
+            (libc::ST_RELATIME, libc::MS_RELATIME),
+        ];
+        Ok(mapping.iter()
+            .filter(|&&(st, _)| stat.f_flag & st != 0)
+            .fold(0, |flags, &(_, ms)| flags | ms))
+    }
+}
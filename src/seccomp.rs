@@ -0,0 +1,760 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+use std::io;
+use std::process::{Command, ExitStatus};
+use std::thread::JoinHandle;
+
+/// System calls allowed by the default profile.
+const DEFAULT_ALLOWED: &[&str] = &[
+    "access", "arch_prctl", "brk", "clock_getres", "clock_gettime",
+    "clock_nanosleep", "close", "dup", "dup2", "dup3", "exit", "exit_group",
+    "faccessat", "faccessat2", "fcntl", "fstat", "futex", "get_robust_list",
+    "getcwd", "getdents64", "getegid", "geteuid", "getgid", "getpid",
+    "getppid", "getrandom", "getrlimit", "gettid", "gettimeofday", "getuid",
+    "lseek", "lstat", "madvise", "membarrier", "mmap", "mprotect", "mremap",
+    "munmap", "nanosleep", "newfstatat", "open", "openat", "pipe", "pipe2",
+    "poll", "ppoll", "pread64", "prlimit64", "pwrite64", "read", "readlink",
+    "readlinkat", "readv", "rseq", "rt_sigaction", "rt_sigprocmask",
+    "rt_sigreturn", "sched_getaffinity", "sched_yield", "set_robust_list",
+    "set_tid_address", "sigaltstack", "stat", "statx", "tgkill", "uname",
+    "write", "writev",
+];
+
+/// seccomp-bpf system call filter applied to the evaluated program.
+///
+/// A profile is an allowlist of system calls. A forbidden system call makes
+/// the evaluation fail with `EvalError::SyscallBlocked`.
+///
+/// The filter is installed right before the program is executed, and
+/// forbidden system calls are reported to the evaluator, which kills the
+/// program. In addition to the allowed system calls, the program can always
+/// create threads (`clone` with `CLONE_THREAD`), but not other processes.
+/// `clone3` fails with `ENOSYS` so that the C library falls back to `clone`.
+/// `execve` and `execveat` are always forbidden, even if allowed, and so is
+/// `seccomp` with flags.
+///
+/// The default profile permits typical computation, memory management,
+/// threads, time, and I/O on already opened or regular files (including
+/// stdout and stderr). It forbids in particular `socket`, `ptrace`, `mount`
+/// and process creation.
+///
+/// Filters are only supported on Linux on x86_64 and aarch64.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use everust::{EvalError, Evaluator, SeccompProfile};
+/// let evaluator = Evaluator::builder()
+///     .seccomp(SeccompProfile::new())
+///     .build();
+/// match evaluator.eval(r#"std::net::TcpListener::bind("127.0.0.1:0")"#) {
+///     Err(EvalError::SyscallBlocked {syscall, ..}) => {
+///         assert_eq!(Some("socket"), syscall.as_ref().map(|s| &s[..]));
+///     }
+///     _ => panic!(),
+/// }
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SeccompProfile {
+    allowed: Vec<String>,
+}
+
+impl SeccompProfile {
+    /// Returns the default profile.
+    pub fn new() -> SeccompProfile {
+        SeccompProfile {
+            allowed: DEFAULT_ALLOWED.iter().map(|&s| s.to_owned()).collect(),
+        }
+    }
+
+    /// Returns a profile that allows no system call.
+    ///
+    /// Even simple programs need many system calls to start, so system calls
+    /// must be added with `allow`.
+    pub fn empty() -> SeccompProfile {
+        SeccompProfile {allowed: Vec::new()}
+    }
+
+    /// Allows a system call, given by name (e.g. `"socket"`).
+    ///
+    /// Evaluation fails if the name is unknown.
+    pub fn allow<S: Into<String>>(mut self, syscall: S) -> SeccompProfile {
+        let syscall = syscall.into();
+        if !self.allowed.contains(&syscall) {
+            self.allowed.push(syscall);
+        }
+        self
+    }
+
+    /// Forbids a system call, given by name.
+    pub fn forbid(mut self, syscall: &str) -> SeccompProfile {
+        self.allowed.retain(|s| s != syscall);
+        self
+    }
+
+    /// Returns the allowed system calls.
+    pub fn allowed(&self) -> &[String] {
+        &self.allowed
+    }
+}
+
+impl Default for SeccompProfile {
+    fn default() -> SeccompProfile {
+        SeccompProfile::new()
+    }
+}
+
+/// Monitor of the forbidden system calls made by a program, running on a
+/// thread of the current process.
+pub struct Monitor {
+    thread: JoinHandle<Option<i64>>,
+}
+
+impl Monitor {
+    /// Returns the number of the first forbidden system call made by the
+    /// program, once all its processes have exited.
+    pub fn finish(self) -> Option<i64> {
+        self.thread.join().unwrap_or(None)
+    }
+}
+
+/// Determines if the program failed because of a forbidden system call.
+///
+/// `nr` is the forbidden system call reported by the monitor. Returns `None`
+/// if not, and `Some(name)` otherwise, where `name` is the name of the system
+/// call, or its number if the name is unknown, or `None` if it could not be
+/// determined.
+pub fn blocked(nr: Option<i64>, status: &ExitStatus) -> Option<Option<String>> {
+    match nr {
+        Some(nr) => {
+            Some(Some(syscall_name(nr).map_or_else(|| nr.to_string(),
+                |name| name.to_owned())))
+        }
+        None if killed_by_sigsys(status) => Some(None),
+        None => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn killed_by_sigsys(status: &ExitStatus) -> bool {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal() == Some(libc::SIGSYS)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn killed_by_sigsys(_: &ExitStatus) -> bool {
+    false
+}
+
+/// Configures the command so that the filter is installed right before the
+/// program is executed, and returns the monitor of the forbidden system calls.
+///
+/// Forbidden system calls, including `execve`, are passed to the monitor,
+/// which only lets the first `execve` (that of the program) through, and
+/// kills the program on any other. This must be the last configuration
+/// applied to the command.
+#[cfg(all(target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")))]
+pub fn apply(cmd: &mut Command, profile: &SeccompProfile)
+    -> io::Result<Monitor>
+{
+    use std::os::unix::process::CommandExt;
+
+    let allowed = profile.allowed.iter()
+        .map(|name| syscall_number(name).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput,
+                format!("Unknown syscall: {}", name))
+        }))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (parent, child) = linux::socket_pair()?;
+    let installer = linux::Installer::new(&allowed, child);
+    let thread = std::thread::Builder::new()
+        .name("everust-seccomp".to_owned())
+        .spawn(move || linux::monitor(parent))?;
+    unsafe {
+        cmd.pre_exec(move || installer.run());
+    }
+    Ok(Monitor {thread})
+}
+
+#[cfg(not(all(target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64"))))]
+pub fn apply(_: &mut Command, _: &SeccompProfile) -> io::Result<Monitor> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+        "seccomp filters are only supported on Linux on x86_64 and aarch64"))
+}
+
+#[cfg(all(target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod linux {
+    use libc::{c_int, c_long, c_void, sock_filter, sock_fprog};
+    use std::io;
+    use std::mem;
+    use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xc000_003e;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+    // Offsets in `struct seccomp_data`.
+    const NR_OFFSET: u32 = 0;
+    const ARCH_OFFSET: u32 = 4;
+    const ARG0_LOW_OFFSET: u32 = 16;
+    const ARG1_LOW_OFFSET: u32 = 24;
+    const ARG1_HIGH_OFFSET: u32 = 28;
+
+    fn stmt(code: u32, k: u32) -> sock_filter {
+        sock_filter {code: code as u16, jt: 0, jf: 0, k}
+    }
+
+    fn jump(code: u32, k: u32, jt: u8, jf: u8) -> sock_filter {
+        sock_filter {code: code as u16, jt, jf, k}
+    }
+
+    fn load(offset: u32) -> sock_filter {
+        stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, offset)
+    }
+
+    fn ret(action: u32) -> sock_filter {
+        stmt(libc::BPF_RET | libc::BPF_K, action)
+    }
+
+    fn jump_if_eq(k: u32, jt: u8, jf: u8) -> sock_filter {
+        jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, k, jt, jf)
+    }
+
+    /// Builds the filter passing forbidden system calls to the monitor.
+    ///
+    /// `execve` and `execveat` are checked before the allowed system calls,
+    /// so that they always go through the monitor. `seccomp` is allowed
+    /// without flags, to install `sendmsg_filter`, but not with a listener,
+    /// which would let the program answer its own notifications. `sendmsg`
+    /// is allowed to send the listener to the monitor.
+    fn filter(allowed: &[c_long]) -> Vec<sock_filter> {
+        let notify = libc::SECCOMP_RET_USER_NOTIF;
+        let allow = libc::SECCOMP_RET_ALLOW;
+        let mut filter = vec![
+            load(ARCH_OFFSET),
+            jump_if_eq(AUDIT_ARCH, 1, 0),
+            ret(libc::SECCOMP_RET_KILL_PROCESS),
+            load(NR_OFFSET),
+        ];
+        #[cfg(target_arch = "x86_64")]
+        filter.extend(vec![
+            // x32 system calls
+            jump(libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K, 0x4000_0000, 0,
+                1),
+            ret(notify),
+        ]);
+        filter.extend(vec![
+            jump_if_eq(libc::SYS_execve as u32, 0, 1),
+            ret(notify),
+            jump_if_eq(libc::SYS_execveat as u32, 0, 1),
+            ret(notify),
+            jump_if_eq(libc::SYS_seccomp as u32, 0, 6),
+            load(ARG1_LOW_OFFSET),
+            jump_if_eq(0, 0, 3),
+            load(ARG1_HIGH_OFFSET),
+            jump_if_eq(0, 0, 1),
+            ret(allow),
+            ret(notify),
+        ]);
+        for &nr in allowed {
+            filter.push(jump_if_eq(nr as u32, 0, 1));
+            filter.push(ret(allow));
+        }
+        filter.extend(vec![
+            jump_if_eq(libc::SYS_sendmsg as u32, 0, 1),
+            ret(allow),
+            jump_if_eq(libc::SYS_clone3 as u32, 0, 1),
+            ret(libc::SECCOMP_RET_ERRNO | libc::ENOSYS as u32),
+            jump_if_eq(libc::SYS_clone as u32, 0, 3),
+            load(ARG0_LOW_OFFSET),
+            jump(libc::BPF_JMP | libc::BPF_JSET | libc::BPF_K,
+                libc::CLONE_THREAD as u32, 0, 1),
+            ret(allow),
+            ret(notify),
+        ]);
+        filter
+    }
+
+    /// Builds the filter forbidding `sendmsg` once the listener is sent, if
+    /// it is not allowed.
+    fn sendmsg_filter(allowed: &[c_long]) -> Option<Vec<sock_filter>> {
+        if allowed.contains(&libc::SYS_sendmsg) {
+            return None
+        }
+        Some(vec![
+            load(NR_OFFSET),
+            jump_if_eq(libc::SYS_sendmsg as u32, 0, 1),
+            ret(libc::SECCOMP_RET_KILL_PROCESS),
+            ret(libc::SECCOMP_RET_ALLOW),
+        ])
+    }
+
+    /// Returns a pair of connected sockets, for the monitor and the child.
+    pub fn socket_pair() -> io::Result<(OwnedFd, OwnedFd)> {
+        let mut fds = [0; 2];
+        check(unsafe {
+            libc::socketpair(libc::AF_UNIX,
+                libc::SOCK_SEQPACKET | libc::SOCK_CLOEXEC, 0, fds.as_mut_ptr())
+        })?;
+        unsafe {
+            Ok((OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])))
+        }
+    }
+
+    /// Everything needed to install the filters, prepared before forking as
+    /// only async-signal-safe operations are allowed in the child.
+    pub struct Installer {
+        filter: Vec<sock_filter>,
+        sendmsg_filter: Option<Vec<sock_filter>>,
+        socket: OwnedFd,
+    }
+
+    // The filters do not point to anything.
+    unsafe impl Send for Installer {}
+    unsafe impl Sync for Installer {}
+
+    impl Installer {
+        pub fn new(allowed: &[c_long], socket: OwnedFd) -> Installer {
+            Installer {
+                filter: filter(allowed),
+                sendmsg_filter: sendmsg_filter(allowed),
+                socket,
+            }
+        }
+
+        /// Installs the filters and sends the listener to the monitor. Runs
+        /// in the child process after fork.
+        ///
+        /// The listener and the socket are closed when the program is
+        /// executed.
+        pub fn run(&self) -> io::Result<()> {
+            check(unsafe {
+                libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0)
+            })?;
+            let listener = install(&self.filter,
+                libc::SECCOMP_FILTER_FLAG_NEW_LISTENER)?;
+            send_fd(self.socket.as_raw_fd(), listener)?;
+            if let Some(ref filter) = self.sendmsg_filter {
+                install(filter, 0)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Installs a filter in the current process and returns the result of
+    /// the `seccomp` system call.
+    fn install(filter: &[sock_filter], flags: libc::c_ulong)
+        -> io::Result<c_int>
+    {
+        let prog = sock_fprog {
+            len: filter.len() as u16,
+            filter: filter.as_ptr() as *mut _,
+        };
+        let r = unsafe {
+            libc::syscall(libc::SYS_seccomp, libc::SECCOMP_SET_MODE_FILTER,
+                flags, &prog as *const sock_fprog)
+        };
+        check(r as c_int)
+    }
+
+    /// Buffer for a control message carrying a file descriptor.
+    #[repr(C)]
+    struct FdMessage {
+        header: libc::cmsghdr,
+        fd: c_int,
+        _padding: c_int,
+    }
+
+    /// Returns a message with one byte of data and room for a file
+    /// descriptor.
+    fn message(iov: &mut libc::iovec, control: &mut FdMessage)
+        -> libc::msghdr
+    {
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_iov = iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control as *mut FdMessage as *mut c_void;
+        msg.msg_controllen = mem::size_of::<FdMessage>() as _;
+        msg
+    }
+
+    fn send_fd(socket: c_int, fd: c_int) -> io::Result<()> {
+        let mut data = 0u8;
+        let mut iov = libc::iovec {
+            iov_base: &mut data as *mut u8 as *mut c_void,
+            iov_len: 1,
+        };
+        let mut control: FdMessage = unsafe { mem::zeroed() };
+        control.header.cmsg_level = libc::SOL_SOCKET;
+        control.header.cmsg_type = libc::SCM_RIGHTS;
+        control.header.cmsg_len = unsafe {
+            libc::CMSG_LEN(mem::size_of::<c_int>() as u32)
+        } as _;
+        control.fd = fd;
+        let msg = message(&mut iov, &mut control);
+        check(unsafe { libc::sendmsg(socket, &msg, 0) } as c_int)?;
+        Ok(())
+    }
+
+    fn recv_fd(socket: c_int) -> Option<OwnedFd> {
+        let mut data = 0u8;
+        let mut iov = libc::iovec {
+            iov_base: &mut data as *mut u8 as *mut c_void,
+            iov_len: 1,
+        };
+        let mut control: FdMessage = unsafe { mem::zeroed() };
+        let mut msg = message(&mut iov, &mut control);
+        let n = unsafe {
+            libc::recvmsg(socket, &mut msg, libc::MSG_CMSG_CLOEXEC)
+        };
+        if n <= 0 || control.header.cmsg_level != libc::SOL_SOCKET
+            || control.header.cmsg_type != libc::SCM_RIGHTS
+        {
+            return None
+        }
+        Some(unsafe { OwnedFd::from_raw_fd(control.fd) })
+    }
+
+    /// Handles the notifications of forbidden system calls until all the
+    /// processes of the program have exited, and returns the number of the
+    /// first one.
+    ///
+    /// Only the first notification can let `execve` through, as it comes
+    /// from the child executing the program. Any other forbidden system call
+    /// fails and its process is killed.
+    pub fn monitor(socket: OwnedFd) -> Option<i64> {
+        let listener = recv_fd(socket.as_raw_fd())?;
+        drop(socket);
+        let listener = listener.as_raw_fd();
+        let mut first = true;
+        let mut blocked = None;
+        loop {
+            let mut poll_fd = libc::pollfd {
+                fd: listener,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            if unsafe { libc::poll(&mut poll_fd, 1, -1) } < 0 {
+                if io::Error::last_os_error().kind()
+                    == io::ErrorKind::Interrupted
+                {
+                    continue
+                }
+                break
+            }
+            // The listener hangs up when no process uses the filter anymore.
+            if poll_fd.revents & libc::POLLIN == 0 {
+                break
+            }
+            let mut notif: libc::seccomp_notif = unsafe { mem::zeroed() };
+            let r = unsafe {
+                libc::ioctl(listener, libc::SECCOMP_IOCTL_NOTIF_RECV,
+                    &mut notif)
+            };
+            if r < 0 {
+                // The process was interrupted or killed meanwhile.
+                continue
+            }
+            let mut resp = libc::seccomp_notif_resp {
+                id: notif.id,
+                val: 0,
+                error: 0,
+                flags: 0,
+            };
+            if first && notif.data.nr == libc::SYS_execve as c_int {
+                resp.flags = libc::SECCOMP_USER_NOTIF_FLAG_CONTINUE as u32;
+            } else {
+                blocked.get_or_insert(notif.data.nr as i64);
+                resp.error = -libc::EPERM;
+                let valid = unsafe {
+                    libc::ioctl(listener, libc::SECCOMP_IOCTL_NOTIF_ID_VALID,
+                        &notif.id)
+                };
+                if valid == 0 {
+                    unsafe { libc::kill(notif.pid as libc::pid_t,
+                        libc::SIGKILL); }
+                }
+            }
+            first = false;
+            unsafe {
+                libc::ioctl(listener, libc::SECCOMP_IOCTL_NOTIF_SEND,
+                    &mut resp);
+            }
+        }
+        blocked
+    }
+
+    fn check(r: c_int) -> io::Result<c_int> {
+        if r < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(r)
+        }
+    }
+}
+
+/// Names and numbers of the system calls available on all supported
+/// architectures.
+#[cfg(all(target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")))]
+const COMMON_SYSCALLS: &[(&str, libc::c_long)] = &[
+    ("accept", libc::SYS_accept), ("accept4", libc::SYS_accept4),
+    ("acct", libc::SYS_acct), ("add_key", libc::SYS_add_key),
+    ("adjtimex", libc::SYS_adjtimex), ("bind", libc::SYS_bind),
+    ("bpf", libc::SYS_bpf), ("brk", libc::SYS_brk),
+    ("capget", libc::SYS_capget), ("capset", libc::SYS_capset),
+    ("chdir", libc::SYS_chdir), ("chroot", libc::SYS_chroot),
+    ("clock_adjtime", libc::SYS_clock_adjtime),
+    ("clock_getres", libc::SYS_clock_getres),
+    ("clock_gettime", libc::SYS_clock_gettime),
+    ("clock_nanosleep", libc::SYS_clock_nanosleep),
+    ("clock_settime", libc::SYS_clock_settime), ("clone", libc::SYS_clone),
+    ("clone3", libc::SYS_clone3), ("close", libc::SYS_close),
+    ("close_range", libc::SYS_close_range), ("connect", libc::SYS_connect),
+    ("copy_file_range", libc::SYS_copy_file_range),
+    ("delete_module", libc::SYS_delete_module), ("dup", libc::SYS_dup),
+    ("dup3", libc::SYS_dup3), ("epoll_create1", libc::SYS_epoll_create1),
+    ("epoll_ctl", libc::SYS_epoll_ctl), ("epoll_pwait", libc::SYS_epoll_pwait),
+    ("epoll_pwait2", libc::SYS_epoll_pwait2), ("eventfd2", libc::SYS_eventfd2),
+    ("execve", libc::SYS_execve), ("execveat", libc::SYS_execveat),
+    ("exit", libc::SYS_exit), ("exit_group", libc::SYS_exit_group),
+    ("faccessat", libc::SYS_faccessat), ("faccessat2", libc::SYS_faccessat2),
+    ("fadvise64", libc::SYS_fadvise64), ("fallocate", libc::SYS_fallocate),
+    ("fanotify_init", libc::SYS_fanotify_init),
+    ("fanotify_mark", libc::SYS_fanotify_mark), ("fchdir", libc::SYS_fchdir),
+    ("fchmod", libc::SYS_fchmod), ("fchmodat", libc::SYS_fchmodat),
+    ("fchown", libc::SYS_fchown), ("fchownat", libc::SYS_fchownat),
+    ("fcntl", libc::SYS_fcntl), ("fdatasync", libc::SYS_fdatasync),
+    ("fgetxattr", libc::SYS_fgetxattr),
+    ("finit_module", libc::SYS_finit_module),
+    ("flistxattr", libc::SYS_flistxattr), ("flock", libc::SYS_flock),
+    ("fremovexattr", libc::SYS_fremovexattr), ("fsconfig", libc::SYS_fsconfig),
+    ("fsetxattr", libc::SYS_fsetxattr), ("fsmount", libc::SYS_fsmount),
+    ("fsopen", libc::SYS_fsopen), ("fspick", libc::SYS_fspick),
+    ("fstat", libc::SYS_fstat), ("fstatfs", libc::SYS_fstatfs),
+    ("fsync", libc::SYS_fsync), ("ftruncate", libc::SYS_ftruncate),
+    ("futex", libc::SYS_futex), ("futex_waitv", libc::SYS_futex_waitv),
+    ("get_mempolicy", libc::SYS_get_mempolicy),
+    ("get_robust_list", libc::SYS_get_robust_list),
+    ("getcpu", libc::SYS_getcpu), ("getcwd", libc::SYS_getcwd),
+    ("getdents64", libc::SYS_getdents64), ("getegid", libc::SYS_getegid),
+    ("geteuid", libc::SYS_geteuid), ("getgid", libc::SYS_getgid),
+    ("getgroups", libc::SYS_getgroups), ("getitimer", libc::SYS_getitimer),
+    ("getpeername", libc::SYS_getpeername), ("getpgid", libc::SYS_getpgid),
+    ("getpid", libc::SYS_getpid), ("getppid", libc::SYS_getppid),
+    ("getpriority", libc::SYS_getpriority), ("getrandom", libc::SYS_getrandom),
+    ("getresgid", libc::SYS_getresgid), ("getresuid", libc::SYS_getresuid),
+    ("getrusage", libc::SYS_getrusage), ("getsid", libc::SYS_getsid),
+    ("getsockname", libc::SYS_getsockname),
+    ("getsockopt", libc::SYS_getsockopt), ("gettid", libc::SYS_gettid),
+    ("gettimeofday", libc::SYS_gettimeofday), ("getuid", libc::SYS_getuid),
+    ("getxattr", libc::SYS_getxattr), ("init_module", libc::SYS_init_module),
+    ("inotify_add_watch", libc::SYS_inotify_add_watch),
+    ("inotify_init1", libc::SYS_inotify_init1),
+    ("inotify_rm_watch", libc::SYS_inotify_rm_watch),
+    ("io_cancel", libc::SYS_io_cancel), ("io_destroy", libc::SYS_io_destroy),
+    ("io_getevents", libc::SYS_io_getevents), ("io_setup", libc::SYS_io_setup),
+    ("io_submit", libc::SYS_io_submit),
+    ("io_uring_enter", libc::SYS_io_uring_enter),
+    ("io_uring_register", libc::SYS_io_uring_register),
+    ("io_uring_setup", libc::SYS_io_uring_setup), ("ioctl", libc::SYS_ioctl),
+    ("ioprio_get", libc::SYS_ioprio_get), ("ioprio_set", libc::SYS_ioprio_set),
+    ("kcmp", libc::SYS_kcmp), ("kexec_file_load", libc::SYS_kexec_file_load),
+    ("kexec_load", libc::SYS_kexec_load), ("keyctl", libc::SYS_keyctl),
+    ("kill", libc::SYS_kill),
+    ("landlock_add_rule", libc::SYS_landlock_add_rule),
+    ("landlock_create_ruleset", libc::SYS_landlock_create_ruleset),
+    ("landlock_restrict_self", libc::SYS_landlock_restrict_self),
+    ("lgetxattr", libc::SYS_lgetxattr), ("linkat", libc::SYS_linkat),
+    ("listen", libc::SYS_listen), ("listxattr", libc::SYS_listxattr),
+    ("llistxattr", libc::SYS_llistxattr),
+    ("lookup_dcookie", libc::SYS_lookup_dcookie),
+    ("lremovexattr", libc::SYS_lremovexattr), ("lseek", libc::SYS_lseek),
+    ("lsetxattr", libc::SYS_lsetxattr), ("madvise", libc::SYS_madvise),
+    ("mbind", libc::SYS_mbind), ("membarrier", libc::SYS_membarrier),
+    ("memfd_create", libc::SYS_memfd_create),
+    ("memfd_secret", libc::SYS_memfd_secret),
+    ("migrate_pages", libc::SYS_migrate_pages), ("mincore", libc::SYS_mincore),
+    ("mkdirat", libc::SYS_mkdirat), ("mknodat", libc::SYS_mknodat),
+    ("mlock", libc::SYS_mlock), ("mlock2", libc::SYS_mlock2),
+    ("mlockall", libc::SYS_mlockall), ("mmap", libc::SYS_mmap),
+    ("mount", libc::SYS_mount), ("mount_setattr", libc::SYS_mount_setattr),
+    ("move_mount", libc::SYS_move_mount), ("move_pages", libc::SYS_move_pages),
+    ("mprotect", libc::SYS_mprotect),
+    ("mq_getsetattr", libc::SYS_mq_getsetattr),
+    ("mq_notify", libc::SYS_mq_notify), ("mq_open", libc::SYS_mq_open),
+    ("mq_timedreceive", libc::SYS_mq_timedreceive),
+    ("mq_timedsend", libc::SYS_mq_timedsend),
+    ("mq_unlink", libc::SYS_mq_unlink), ("mremap", libc::SYS_mremap),
+    ("mseal", libc::SYS_mseal), ("msgctl", libc::SYS_msgctl),
+    ("msgget", libc::SYS_msgget), ("msgrcv", libc::SYS_msgrcv),
+    ("msgsnd", libc::SYS_msgsnd), ("msync", libc::SYS_msync),
+    ("munlock", libc::SYS_munlock), ("munlockall", libc::SYS_munlockall),
+    ("munmap", libc::SYS_munmap),
+    ("name_to_handle_at", libc::SYS_name_to_handle_at),
+    ("nanosleep", libc::SYS_nanosleep), ("newfstatat", libc::SYS_newfstatat),
+    ("nfsservctl", libc::SYS_nfsservctl),
+    ("open_by_handle_at", libc::SYS_open_by_handle_at),
+    ("open_tree", libc::SYS_open_tree), ("openat", libc::SYS_openat),
+    ("openat2", libc::SYS_openat2),
+    ("perf_event_open", libc::SYS_perf_event_open),
+    ("personality", libc::SYS_personality),
+    ("pidfd_getfd", libc::SYS_pidfd_getfd),
+    ("pidfd_open", libc::SYS_pidfd_open),
+    ("pidfd_send_signal", libc::SYS_pidfd_send_signal),
+    ("pipe2", libc::SYS_pipe2), ("pivot_root", libc::SYS_pivot_root),
+    ("pkey_alloc", libc::SYS_pkey_alloc), ("pkey_free", libc::SYS_pkey_free),
+    ("pkey_mprotect", libc::SYS_pkey_mprotect), ("ppoll", libc::SYS_ppoll),
+    ("prctl", libc::SYS_prctl), ("pread64", libc::SYS_pread64),
+    ("preadv", libc::SYS_preadv), ("preadv2", libc::SYS_preadv2),
+    ("prlimit64", libc::SYS_prlimit64),
+    ("process_madvise", libc::SYS_process_madvise),
+    ("process_mrelease", libc::SYS_process_mrelease),
+    ("process_vm_readv", libc::SYS_process_vm_readv),
+    ("process_vm_writev", libc::SYS_process_vm_writev),
+    ("pselect6", libc::SYS_pselect6), ("ptrace", libc::SYS_ptrace),
+    ("pwrite64", libc::SYS_pwrite64), ("pwritev", libc::SYS_pwritev),
+    ("pwritev2", libc::SYS_pwritev2), ("quotactl", libc::SYS_quotactl),
+    ("quotactl_fd", libc::SYS_quotactl_fd), ("read", libc::SYS_read),
+    ("readahead", libc::SYS_readahead), ("readlinkat", libc::SYS_readlinkat),
+    ("readv", libc::SYS_readv), ("reboot", libc::SYS_reboot),
+    ("recvfrom", libc::SYS_recvfrom), ("recvmmsg", libc::SYS_recvmmsg),
+    ("recvmsg", libc::SYS_recvmsg),
+    ("remap_file_pages", libc::SYS_remap_file_pages),
+    ("removexattr", libc::SYS_removexattr), ("renameat2", libc::SYS_renameat2),
+    ("request_key", libc::SYS_request_key),
+    ("restart_syscall", libc::SYS_restart_syscall), ("rseq", libc::SYS_rseq),
+    ("rt_sigaction", libc::SYS_rt_sigaction),
+    ("rt_sigpending", libc::SYS_rt_sigpending),
+    ("rt_sigprocmask", libc::SYS_rt_sigprocmask),
+    ("rt_sigqueueinfo", libc::SYS_rt_sigqueueinfo),
+    ("rt_sigreturn", libc::SYS_rt_sigreturn),
+    ("rt_sigsuspend", libc::SYS_rt_sigsuspend),
+    ("rt_sigtimedwait", libc::SYS_rt_sigtimedwait),
+    ("rt_tgsigqueueinfo", libc::SYS_rt_tgsigqueueinfo),
+    ("sched_get_priority_max", libc::SYS_sched_get_priority_max),
+    ("sched_get_priority_min", libc::SYS_sched_get_priority_min),
+    ("sched_getaffinity", libc::SYS_sched_getaffinity),
+    ("sched_getattr", libc::SYS_sched_getattr),
+    ("sched_getparam", libc::SYS_sched_getparam),
+    ("sched_getscheduler", libc::SYS_sched_getscheduler),
+    ("sched_rr_get_interval", libc::SYS_sched_rr_get_interval),
+    ("sched_setaffinity", libc::SYS_sched_setaffinity),
+    ("sched_setattr", libc::SYS_sched_setattr),
+    ("sched_setparam", libc::SYS_sched_setparam),
+    ("sched_setscheduler", libc::SYS_sched_setscheduler),
+    ("sched_yield", libc::SYS_sched_yield), ("seccomp", libc::SYS_seccomp),
+    ("semctl", libc::SYS_semctl), ("semget", libc::SYS_semget),
+    ("semop", libc::SYS_semop), ("semtimedop", libc::SYS_semtimedop),
+    ("sendfile", libc::SYS_sendfile), ("sendmmsg", libc::SYS_sendmmsg),
+    ("sendmsg", libc::SYS_sendmsg), ("sendto", libc::SYS_sendto),
+    ("set_mempolicy", libc::SYS_set_mempolicy),
+    ("set_mempolicy_home_node", libc::SYS_set_mempolicy_home_node),
+    ("set_robust_list", libc::SYS_set_robust_list),
+    ("set_tid_address", libc::SYS_set_tid_address),
+    ("setdomainname", libc::SYS_setdomainname),
+    ("setfsgid", libc::SYS_setfsgid), ("setfsuid", libc::SYS_setfsuid),
+    ("setgid", libc::SYS_setgid), ("setgroups", libc::SYS_setgroups),
+    ("sethostname", libc::SYS_sethostname), ("setitimer", libc::SYS_setitimer),
+    ("setns", libc::SYS_setns), ("setpgid", libc::SYS_setpgid),
+    ("setpriority", libc::SYS_setpriority), ("setregid", libc::SYS_setregid),
+    ("setresgid", libc::SYS_setresgid), ("setresuid", libc::SYS_setresuid),
+    ("setreuid", libc::SYS_setreuid), ("setsid", libc::SYS_setsid),
+    ("setsockopt", libc::SYS_setsockopt),
+    ("settimeofday", libc::SYS_settimeofday), ("setuid", libc::SYS_setuid),
+    ("setxattr", libc::SYS_setxattr), ("shmat", libc::SYS_shmat),
+    ("shmctl", libc::SYS_shmctl), ("shmdt", libc::SYS_shmdt),
+    ("shmget", libc::SYS_shmget), ("shutdown", libc::SYS_shutdown),
+    ("sigaltstack", libc::SYS_sigaltstack), ("signalfd4", libc::SYS_signalfd4),
+    ("socket", libc::SYS_socket), ("socketpair", libc::SYS_socketpair),
+    ("splice", libc::SYS_splice), ("statfs", libc::SYS_statfs),
+    ("statx", libc::SYS_statx), ("swapoff", libc::SYS_swapoff),
+    ("swapon", libc::SYS_swapon), ("symlinkat", libc::SYS_symlinkat),
+    ("sync", libc::SYS_sync), ("syncfs", libc::SYS_syncfs),
+    ("sysinfo", libc::SYS_sysinfo), ("syslog", libc::SYS_syslog),
+    ("tee", libc::SYS_tee), ("tgkill", libc::SYS_tgkill),
+    ("timer_create", libc::SYS_timer_create),
+    ("timer_delete", libc::SYS_timer_delete),
+    ("timer_getoverrun", libc::SYS_timer_getoverrun),
+    ("timer_gettime", libc::SYS_timer_gettime),
+    ("timer_settime", libc::SYS_timer_settime),
+    ("timerfd_create", libc::SYS_timerfd_create),
+    ("timerfd_gettime", libc::SYS_timerfd_gettime),
+    ("timerfd_settime", libc::SYS_timerfd_settime), ("times", libc::SYS_times),
+    ("tkill", libc::SYS_tkill), ("truncate", libc::SYS_truncate),
+    ("umask", libc::SYS_umask), ("umount2", libc::SYS_umount2),
+    ("uname", libc::SYS_uname), ("unlinkat", libc::SYS_unlinkat),
+    ("unshare", libc::SYS_unshare), ("userfaultfd", libc::SYS_userfaultfd),
+    ("utimensat", libc::SYS_utimensat), ("vhangup", libc::SYS_vhangup),
+    ("vmsplice", libc::SYS_vmsplice), ("wait4", libc::SYS_wait4),
+    ("waitid", libc::SYS_waitid), ("write", libc::SYS_write),
+    ("writev", libc::SYS_writev),
+];
+
+/// Names and numbers of the system calls specific to x86_64.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+const ARCH_SYSCALLS: &[(&str, libc::c_long)] = &[
+    ("_sysctl", libc::SYS__sysctl), ("access", libc::SYS_access),
+    ("afs_syscall", libc::SYS_afs_syscall), ("alarm", libc::SYS_alarm),
+    ("arch_prctl", libc::SYS_arch_prctl), ("chmod", libc::SYS_chmod),
+    ("chown", libc::SYS_chown), ("creat", libc::SYS_creat),
+    ("dup2", libc::SYS_dup2), ("epoll_create", libc::SYS_epoll_create),
+    ("epoll_ctl_old", libc::SYS_epoll_ctl_old),
+    ("epoll_wait", libc::SYS_epoll_wait),
+    ("epoll_wait_old", libc::SYS_epoll_wait_old),
+    ("eventfd", libc::SYS_eventfd), ("fchmodat2", libc::SYS_fchmodat2),
+    ("fork", libc::SYS_fork), ("futimesat", libc::SYS_futimesat),
+    ("get_thread_area", libc::SYS_get_thread_area),
+    ("getdents", libc::SYS_getdents), ("getpgrp", libc::SYS_getpgrp),
+    ("getpmsg", libc::SYS_getpmsg), ("getrlimit", libc::SYS_getrlimit),
+    ("inotify_init", libc::SYS_inotify_init), ("ioperm", libc::SYS_ioperm),
+    ("iopl", libc::SYS_iopl), ("lchown", libc::SYS_lchown),
+    ("link", libc::SYS_link), ("lstat", libc::SYS_lstat),
+    ("mkdir", libc::SYS_mkdir), ("mknod", libc::SYS_mknod),
+    ("modify_ldt", libc::SYS_modify_ldt), ("open", libc::SYS_open),
+    ("pause", libc::SYS_pause), ("pipe", libc::SYS_pipe),
+    ("poll", libc::SYS_poll), ("putpmsg", libc::SYS_putpmsg),
+    ("readlink", libc::SYS_readlink), ("rename", libc::SYS_rename),
+    ("renameat", libc::SYS_renameat), ("rmdir", libc::SYS_rmdir),
+    ("security", libc::SYS_security), ("select", libc::SYS_select),
+    ("set_thread_area", libc::SYS_set_thread_area),
+    ("setrlimit", libc::SYS_setrlimit), ("signalfd", libc::SYS_signalfd),
+    ("stat", libc::SYS_stat), ("symlink", libc::SYS_symlink),
+    ("sync_file_range", libc::SYS_sync_file_range), ("sysfs", libc::SYS_sysfs),
+    ("time", libc::SYS_time), ("tuxcall", libc::SYS_tuxcall),
+    ("unlink", libc::SYS_unlink), ("uselib", libc::SYS_uselib),
+    ("ustat", libc::SYS_ustat), ("utime", libc::SYS_utime),
+    ("utimes", libc::SYS_utimes), ("vfork", libc::SYS_vfork),
+    ("vserver", libc::SYS_vserver),
+];
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+const ARCH_SYSCALLS: &[(&str, libc::c_long)] = &[];
+
+#[cfg(all(target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn syscalls() -> impl Iterator<Item = &'static (&'static str, libc::c_long)> {
+    COMMON_SYSCALLS.iter().chain(ARCH_SYSCALLS)
+}
+
+#[cfg(all(target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn syscall_number(name: &str) -> Option<libc::c_long> {
+    syscalls().find(|&&(n, _)| n == name).map(|&(_, nr)| nr)
+}
+
+#[cfg(all(target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn syscall_name(nr: i64) -> Option<&'static str> {
+    syscalls().find(|&&(_, n)| n == nr).map(|&(name, _)| name)
+}
+
+#[cfg(not(all(target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64"))))]
+fn syscall_name(_: i64) -> Option<&'static str> {
+    None
+}
@@ -0,0 +1,118 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+use crate::evaluator::Evaluator;
+use crate::format::OutputFormat;
+use crate::header::{self, Header};
+use crate::output::EvalOutput;
+use crate::syntax;
+use crate::EvalError;
+
+/// Evaluation session keeping bindings and items across evaluations.
+///
+/// Each successful evaluation is recorded, and later evaluations replay the
+/// recorded code before the new code, so that `let` bindings, items and any
+/// other state they construct are available. Only the output of the new code
+/// is returned.
+///
+/// Since recorded code is run again for every evaluation, its side effects
+/// (e.g. writing to a file) are repeated. Code whose evaluation fails is not
+/// recorded.
+///
+/// # Examples
+///
+/// ```rust
+/// use everust::Session;
+/// let mut session = Session::new();
+/// session.eval("let x = 20;").unwrap();
+/// session.eval("fn double(n: i32) -> i32 { n * 2 }").unwrap();
+/// assert_eq!("42", session.eval("double(x + 1)").unwrap().value);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Session {
+    evaluator: Evaluator,
+    history: Vec<String>,
+}
+
+impl Session {
+    /// Returns a session using the default configuration of `Evaluator`.
+    pub fn new() -> Session {
+        Session::with_evaluator(Evaluator::new())
+    }
+
+    /// Returns a session using the given evaluator.
+    pub fn with_evaluator(evaluator: Evaluator) -> Session {
+        Session {evaluator, history: Vec::new()}
+    }
+
+    /// Evaluates rust code in the context of the previous evaluations.
+    ///
+    /// The code is recorded if the evaluation succeeds.
+    pub fn eval(&mut self, code: &str) -> Result<EvalOutput, EvalError> {
+        let (header, prelude) = self.prelude();
+        let output = self.evaluator
+            .compile_with_prelude(&header, &prelude, code)?
+            .run()?;
+        self.history.push(code.to_owned());
+        Ok(output)
+    }
+
+    /// Returns the type of the expression of `code` in the context of the
+    /// previous evaluations, without running it or recording it.
+    ///
+    /// See `Evaluator::type_of`.
+    pub fn type_of(&self, code: &str) -> Result<String, EvalError> {
+        let (header, prelude) = self.prelude();
+        self.evaluator.type_of_with_prelude(&header, &prelude, code)
+    }
+
+    /// Returns the code recorded so far, in order of evaluation.
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Forgets all recorded code.
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+
+    /// Sets how the values of later evaluations are formatted.
+    pub fn set_output_format(&mut self, format: OutputFormat) {
+        self.evaluator.set_output_format(format);
+    }
+
+    /// Returns the evaluator used by the session.
+    pub fn evaluator(&self) -> &Evaluator {
+        &self.evaluator
+    }
+
+    /// Returns the merged headers and the replayed code of the history.
+    fn prelude(&self) -> (Header, String) {
+        let mut header = Header::default();
+        let mut prelude = String::new();
+        for code in &self.history {
+            match header::split(code) {
+                Ok((code_header, body)) => {
+                    header.merge(code_header);
+                    prelude.push_str(&replay(&body));
+                }
+                Err(_) => prelude.push_str(&replay(code)),
+            }
+        }
+        (header, prelude)
+    }
+}
+
+/// Turns recorded code into statements keeping its bindings in scope.
+///
+/// A trailing expression is borrowed rather than moved, so that the values it
+/// refers to remain usable.
+fn replay(code: &str) -> String {
+    let (statements, tail) = syntax::split_tail(code);
+    if tail.trim().is_empty() {
+        format!("{}\n", code)
+    } else if syntax::starts_with_item(tail) {
+        format!("{}\n;\n", code)
+    } else {
+        format!("{}\nlet _ = &(\n{}\n);\n", statements, tail)
+    }
+}
@@ -0,0 +1,435 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+use crate::cache;
+use crate::diagnostic::{Diagnostic, DiagnosticSpan, Level};
+use crate::format::OutputFormat;
+use crate::json;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Name of the generated source file.
+pub const FILE_NAME: &str = "main.rs";
+
+/// Name of the file the value of the expression is written to.
+pub const VALUE_FILE_NAME: &str = "value";
+
+/// Environment variable giving the generated program the path of the file to
+/// write the value to.
+pub const VALUE_ENV: &str = "EVERUST_VALUE";
+
+/// Name of the file the type name of the expression is written to.
+pub const TYPE_FILE_NAME: &str = "type";
+
+/// Environment variable giving the generated program the path of the file to
+/// write the type name of the expression to.
+pub const TYPE_ENV: &str = "EVERUST_TYPE";
+
+/// Start of the label rustc gives to the expression when its type does not
+/// match `()` in a type check.
+const TYPE_LABEL: &str = "this expression has type `";
+
+/// Start of the marker written to stdout and stderr by the generated program
+/// after the prelude has run.
+const REPLAY_MARKER: &str = "\u{0}everust:replayed:";
+
+/// File name used in diagnostics, panic messages and backtraces for locations
+/// in the evaluated code.
+pub const SNIPPET_FILE_NAME: &str = "<snippet>";
+
+/// Parts of the generated source other than the evaluated code.
+#[derive(Clone, Debug)]
+pub struct Template {
+    /// Code run before the evaluated code, whose bindings and items are in
+    /// scope of the evaluated code. What it writes to stdout and stderr can
+    /// be removed with `SourceMap::strip_replayed`.
+    pub prelude: String,
+    /// Statements run just before the evaluated code, in the same block.
+    pub bindings: String,
+    /// How the value of the expression is written.
+    pub format: ValueFormat,
+}
+
+/// Encoding of the value of the expression written by the program.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ValueFormat {
+    /// Formatted as text.
+    Text(OutputFormat),
+    /// Encoded as JSON.
+    Json,
+    /// Not written: the program is only type-checked, and the expression is
+    /// matched against `()` so that rustc reports its type.
+    TypeCheck,
+}
+
+/// Source file generated around the evaluated code.
+#[derive(Clone, Debug)]
+pub struct Source {
+    pub text: String,
+    pub map: SourceMap,
+}
+
+impl Source {
+    /// Generates the source of a program evaluating `code`.
+    ///
+    /// The code is placed on its own lines so that its columns are preserved
+    /// in the generated file.
+    pub fn new(code: &str, template: &Template) -> Source {
+        let mut init = String::new();
+        // The paths are removed from the environment before the prelude
+        // runs, so that the evaluated code does not see them.
+        if template.format != ValueFormat::TypeCheck {
+            init.push_str(&format!("
+    let __everust_type_path = __everust_env::take({:?});
+    let __everust_value_path = __everust_env::take({:?});",
+                TYPE_ENV, VALUE_ENV));
+        }
+        // The marker depends on the code so that the output of the code
+        // cannot be mistaken for it.
+        let replay_marker = if template.prelude.is_empty() {
+            None
+        } else {
+            let key = cache::key([template.prelude.as_bytes(),
+                code.as_bytes()]);
+            Some(format!("{}{}\u{0}", REPLAY_MARKER, &key[..16]))
+        };
+        let prelude = match replay_marker {
+            Some(ref marker) => {
+                format!("{}\n    print!({:?}); eprint!({:?});",
+                    template.prelude, marker, marker)
+            }
+            None => String::new(),
+        };
+        let prefix = format!(r##"
+fn main() {{
+    {}
+    {}
+    let expr = {{
+{}"##, init, prelude, template.bindings);
+        let code = code.trim_end_matches(['\r', '\n']);
+        let suffix = suffix(&template.format);
+        let map = SourceMap {
+            file_name: FILE_NAME.to_owned(),
+            first_line: prefix.matches('\n').count() + 1,
+            line_count: code.matches('\n').count() + 1,
+            byte_offset: prefix.len(),
+            byte_len: code.len(),
+            replay_marker,
+        };
+        Source {text: format!("{}{}{}", prefix, code, suffix), map}
+    }
+
+    /// Writes the source to a file.
+    pub fn write_to(&self, path: &Path) -> io::Result<()> {
+        let mut f = File::create(path)?;
+        f.write_all(self.text.as_bytes())?;
+        f.sync_all()
+    }
+}
+
+/// Returns the code generated after the evaluated code.
+fn suffix(format: &ValueFormat) -> String {
+    let (value, format_items) = match *format {
+        ValueFormat::Text(ref format) => {
+            (format.wrapper_value(), format.wrapper_items())
+        }
+        ValueFormat::Json => {
+            (json::wrapper_value().to_owned(),
+                json::wrapper_items().to_owned())
+        }
+        ValueFormat::TypeCheck => {
+            return "\n    };\n    let () = expr;\n}\n".to_owned()
+        }
+    };
+    format!(r##"
+    }};
+    if let Some(path) = __everust_type_path {{
+        fn type_name_of<T>(_: &T) -> &'static str {{
+            ::std::any::type_name::<T>()
+        }}
+        ::std::fs::write(path, type_name_of(&expr))
+            .expect("everust: cannot write type");
+    }}
+    let value = {};
+    match __everust_value_path {{
+        Some(path) => ::std::fs::write(path, value)
+            .expect("everust: cannot write value"),
+        None => print!("{{}}", value),
+    }}
+}}
+
+mod __everust_env {{
+    #[allow(unused_unsafe)]
+    pub fn take(name: &str) -> Option<::std::ffi::OsString> {{
+        let value = ::std::env::var_os(name);
+        unsafe {{ ::std::env::remove_var(name) }};
+        value
+    }}
+}}
+{}
+"##, value, format_items)
+}
+
+/// Location of the evaluated code in the generated source.
+#[derive(Clone, Debug)]
+pub struct SourceMap {
+    file_name: String,
+    first_line: usize,
+    line_count: usize,
+    byte_offset: usize,
+    byte_len: usize,
+    /// Marker written after the prelude, if the program has one.
+    replay_marker: Option<String>,
+}
+
+impl SourceMap {
+    /// Converts a line of the generated file to a line of the snippet.
+    fn snippet_line(&self, line: usize) -> Option<usize> {
+        if line >= self.first_line && line < self.first_line + self.line_count
+        {
+            Some(line - self.first_line + 1)
+        } else {
+            None
+        }
+    }
+
+    /// Rewrites the spans of diagnostics to refer to the snippet.
+    ///
+    /// Spans outside of the snippet are left untouched, and warnings only
+    /// about code outside of the snippet are removed.
+    pub fn map_diagnostics(&self, diagnostics: &mut Vec<Diagnostic>) {
+        self.map_spans(diagnostics);
+        diagnostics.retain(|diagnostic| {
+            diagnostic.level != Level::Warning || diagnostic.spans.is_empty()
+                || diagnostic.primary_spans()
+                    .any(|span| span.file_name == SNIPPET_FILE_NAME)
+        });
+    }
+
+    fn map_spans(&self, diagnostics: &mut [Diagnostic]) {
+        for diagnostic in diagnostics {
+            for span in &mut diagnostic.spans {
+                self.map_span(span);
+            }
+            self.map_spans(&mut diagnostic.children);
+            if let Some(ref mut rendered) = diagnostic.rendered {
+                *rendered = self.map_rendered(rendered);
+            }
+        }
+    }
+
+    fn map_span(&self, span: &mut DiagnosticSpan) {
+        if span.file_name != self.file_name {
+            return
+        }
+        let lines = (self.snippet_line(span.line_start),
+            self.snippet_line(span.line_end));
+        if let (Some(start), Some(end)) = lines {
+            span.file_name = SNIPPET_FILE_NAME.to_owned();
+            span.line_start = start;
+            span.line_end = end;
+            let offset = self.byte_offset as u32;
+            span.byte_start = span.byte_start.saturating_sub(offset)
+                .min(self.byte_len as u32);
+            span.byte_end = span.byte_end.saturating_sub(offset)
+                .min(self.byte_len as u32);
+        }
+    }
+
+    /// Rewrites locations and line numbers in a diagnostic rendered by rustc.
+    fn map_rendered(&self, rendered: &str) -> String {
+        let mut in_generated_file = false;
+        let mut mapped = String::with_capacity(rendered.len());
+        for line in rendered.split_inclusive('\n') {
+            let trimmed = line.trim_start();
+            if let Some(location) = trimmed.strip_prefix("--> ")
+                .or_else(|| trimmed.strip_prefix("::: "))
+            {
+                in_generated_file = self.parse_location(location).is_some();
+            }
+            match gutter_line_number(line) {
+                Some((width, n, rest)) if in_generated_file => {
+                    match self.snippet_line(n) {
+                        Some(n) => {
+                            mapped.push_str(&format!("{:>1$}", n, width));
+                            mapped.push_str(rest);
+                        }
+                        None => mapped.push_str(line),
+                    }
+                }
+                _ => mapped.push_str(&self.map_locations(line)),
+            }
+        }
+        mapped
+    }
+
+    /// Removes the error reporting the type of the expression from the
+    /// diagnostics of a type check, and returns the type.
+    ///
+    /// Integer and floating point types left to inference are reported as
+    /// the types they default to.
+    pub fn take_type(&self, diagnostics: &mut Vec<Diagnostic>)
+        -> Option<String>
+    {
+        let found = diagnostics.iter().enumerate()
+            .filter(|(_, diagnostic)| diagnostic.level == Level::Error)
+            .flat_map(|(i, diagnostic)| {
+                diagnostic.spans.iter().map(move |span| (i, span))
+            })
+            .filter(|(_, span)| {
+                span.file_name == self.file_name
+                    && self.snippet_line(span.line_start).is_none()
+            })
+            .find_map(|(i, span)| {
+                let ty = span.label.as_ref()?.strip_prefix(TYPE_LABEL)?
+                    .strip_suffix('`')?;
+                Some((i, ty.replace("{integer}", "i32")
+                    .replace("{float}", "f64")))
+            });
+        let (i, ty) = found?;
+        diagnostics.remove(i);
+        Some(ty)
+    }
+
+    /// Rewrites the output of the program to refer to the snippet.
+    ///
+    /// Locations in panic messages and backtraces are rewritten, and frames
+    /// located in the code generated around the snippet are removed from
+    /// backtraces.
+    pub fn map_output(&self, output: &str) -> String {
+        let mut mapped = String::with_capacity(output.len());
+        let mut frame = String::new();
+        let mut frame_hidden = false;
+        for line in output.split_inclusive('\n') {
+            let trimmed = line.trim_start();
+            if is_frame_start(trimmed) {
+                if !frame_hidden {
+                    mapped.push_str(&frame);
+                }
+                frame.clear();
+                frame_hidden = false;
+            } else if frame.is_empty() || !trimmed.starts_with("at ") {
+                if !frame_hidden {
+                    mapped.push_str(&frame);
+                }
+                frame.clear();
+                frame_hidden = false;
+                mapped.push_str(&self.map_locations(line));
+                continue
+            }
+            if let Some(location) = trimmed.strip_prefix("at ") {
+                let location = self.parse_location(location.trim_end());
+                frame_hidden = match location {
+                    Some((line, _, _)) => {
+                        self.snippet_line(line).is_none()
+                    }
+                    None => false,
+                };
+            }
+            frame.push_str(&self.map_locations(line));
+        }
+        if !frame_hidden {
+            mapped.push_str(&frame);
+        }
+        mapped
+    }
+
+    /// Parses a location of the form `file:line:column` in the generated file.
+    ///
+    /// Returns the line, the column and the text following the location.
+    fn parse_location<'a>(&self, s: &'a str)
+        -> Option<(usize, usize, &'a str)>
+    {
+        let s = s.strip_prefix("./").unwrap_or(s);
+        let s = s.strip_prefix(&self.file_name[..])?.strip_prefix(':')?;
+        let (line, rest) = split_number(s)?;
+        let (column, rest) = split_number(rest.strip_prefix(':')?)?;
+        Some((line, column, rest))
+    }
+
+    /// Rewrites all locations in the snippet found in a line of text.
+    fn map_locations(&self, text: &str) -> String {
+        let mut mapped = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(i) = rest.find(&self.file_name[..]) {
+            let (before, candidate) = rest.split_at(i);
+            let start = if before.ends_with("./") {
+                before.len() - 2
+            } else {
+                before.len()
+            };
+            let is_path_char = |c: char| {
+                c.is_alphanumeric() || "_-./\\".contains(c)
+            };
+            let preceded_by_path = before[..start].chars().next_back()
+                .is_some_and(is_path_char);
+            let location = self.parse_location(candidate)
+                .and_then(|(line, column, after)| {
+                    self.snippet_line(line).map(|line| (line, column, after))
+                });
+            match location {
+                Some((line, column, after)) if !preceded_by_path => {
+                    mapped.push_str(&before[..start]);
+                    mapped.push_str(&format!("{}:{}:{}", SNIPPET_FILE_NAME,
+                        line, column));
+                    rest = after;
+                }
+                _ => {
+                    mapped.push_str(before);
+                    mapped.push_str(&self.file_name);
+                    rest = &candidate[self.file_name.len()..];
+                }
+            }
+        }
+        mapped.push_str(rest);
+        mapped
+    }
+
+    /// Removes what was written by the prelude from the output of a program.
+    ///
+    /// The output is left untouched if the program has no prelude.
+    pub fn strip_replayed<'a>(&self, output: &'a str) -> &'a str {
+        let marker = match self.replay_marker {
+            Some(ref marker) => marker,
+            None => return output,
+        };
+        match output.find(&marker[..]) {
+            Some(i) => &output[i + marker.len()..],
+            None => output,
+        }
+    }
+}
+
+fn split_number(s: &str) -> Option<(usize, &str)> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let n = s[..end].parse().ok()?;
+    Some((n, &s[end..]))
+}
+
+/// Parses a line of the form `  12 | code` in a rendered diagnostic, or
+/// `12 + code` in a suggestion.
+///
+/// Returns the width of the gutter before the separator, the line number and
+/// the rest of the line.
+fn gutter_line_number(line: &str) -> Option<(usize, usize, &str)> {
+    let trimmed = line.trim_start_matches(' ');
+    let (n, rest) = split_number(trimmed)?;
+    if !rest.starts_with(' ') {
+        return None
+    }
+    let separator = rest.trim_start_matches(' ');
+    let is_suggestion = ["+ ", "- ", "~ "].iter()
+        .any(|s| separator.starts_with(s));
+    if !separator.starts_with('|') && !is_suggestion {
+        return None
+    }
+    Some((line.len() - rest.len(), n, rest))
+}
+
+/// Checks if a line starts a backtrace frame (e.g. `  3: main::main`).
+fn is_frame_start(trimmed: &str) -> bool {
+    match split_number(trimmed) {
+        Some((_, rest)) => rest.starts_with(": "),
+        None => false,
+    }
+}
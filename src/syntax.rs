@@ -0,0 +1,177 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+/// Result of scanning code.
+#[derive(Clone, Debug, Default)]
+pub struct Scan {
+    /// Delimiters that are still open at the end of the code.
+    pub open: Vec<char>,
+    /// Whether the code ends inside a string literal or block comment.
+    pub unterminated: bool,
+    /// Whether a closing delimiter does not match the open one.
+    pub mismatched: bool,
+    /// Byte offset following the last `;` outside of any delimiter.
+    pub last_statement_end: Option<usize>,
+}
+
+/// Scans code for delimiters and top-level statements.
+pub fn scan(code: &str) -> Scan {
+    let chars = code.char_indices().collect::<Vec<_>>();
+    let mut scan = Scan::default();
+    let mut i = 0;
+    while i < chars.len() {
+        let (pos, c) = chars[i];
+        let next = chars.get(i + 1).map(|&(_, c)| c);
+        match c {
+            '/' if next == Some('/') => {
+                while i < chars.len() && chars[i].1 != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if next == Some('*') => {
+                let mut depth = 0;
+                loop {
+                    match (chars.get(i), chars.get(i + 1)) {
+                        (Some(&(_, '/')), Some(&(_, '*'))) => {
+                            depth += 1;
+                            i += 2;
+                        }
+                        (Some(&(_, '*')), Some(&(_, '/'))) => {
+                            depth -= 1;
+                            i += 2;
+                            if depth == 0 {
+                                break
+                            }
+                        }
+                        (Some(_), _) => i += 1,
+                        (None, _) => {
+                            scan.unterminated = true;
+                            return scan
+                        }
+                    }
+                }
+                continue
+            }
+            '"' => {
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        Some(&(_, '\\')) => i += 2,
+                        Some(&(_, '"')) => break,
+                        Some(_) => i += 1,
+                        None => {
+                            scan.unterminated = true;
+                            return scan
+                        }
+                    }
+                }
+            }
+            'r' if !follows_ident(&chars, i)
+                && matches!(next, Some('"') | Some('#')) =>
+            {
+                let hashes = chars[i + 1..].iter()
+                    .take_while(|&&(_, c)| c == '#')
+                    .count();
+                if chars.get(i + 1 + hashes).map(|&(_, c)| c) == Some('"') {
+                    i += hashes + 2;
+                    loop {
+                        if i >= chars.len() {
+                            scan.unterminated = true;
+                            return scan
+                        }
+                        let closing = chars[i].1 == '"' && chars[i + 1..]
+                            .iter()
+                            .take(hashes)
+                            .filter(|&&(_, c)| c == '#')
+                            .count() == hashes;
+                        if closing {
+                            i += hashes;
+                            break
+                        }
+                        i += 1;
+                    }
+                }
+            }
+            '\'' => {
+                // Distinguishes char literals from lifetimes and labels.
+                if next == Some('\\') {
+                    i += 3;
+                    while i < chars.len() && chars[i].1 != '\'' {
+                        i += 1;
+                    }
+                } else if chars.get(i + 2).map(|&(_, c)| c) == Some('\'') {
+                    i += 2;
+                }
+            }
+            '(' | '[' | '{' => scan.open.push(c),
+            ')' | ']' | '}' => {
+                let expected = match c {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                if scan.open.pop() != Some(expected) {
+                    scan.mismatched = true;
+                }
+            }
+            ';' if scan.open.is_empty() => {
+                scan.last_statement_end = Some(pos + 1);
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    scan
+}
+
+fn follows_ident(chars: &[(usize, char)], i: usize) -> bool {
+    i > 0 && {
+        let c = chars[i - 1].1;
+        c.is_alphanumeric() || c == '_'
+    }
+}
+
+/// Checks if code is complete enough to be evaluated.
+///
+/// Code is incomplete if it has unclosed delimiters, or ends inside a string
+/// literal or block comment. This is meant to detect when more input is
+/// expected in a REPL. Code with mismatched delimiters is considered complete
+/// so that the compiler can report the error.
+///
+/// # Examples
+///
+/// ```rust
+/// use everust::is_complete;
+/// assert!(is_complete("let v = vec![1, 2];"));
+/// assert!(!is_complete("fn f() {"));
+/// assert!(!is_complete("\"abc"));
+/// ```
+pub fn is_complete(code: &str) -> bool {
+    let scan = scan(code);
+    scan.mismatched || (scan.open.is_empty() && !scan.unterminated)
+}
+
+/// Splits code into its statements and its trailing expression, if any.
+pub fn split_tail(code: &str) -> (&str, &str) {
+    let end = scan(code).last_statement_end.unwrap_or(0);
+    code.split_at(end)
+}
+
+/// Checks if code starts with an item or a `let` statement rather than an
+/// expression.
+pub fn starts_with_item(code: &str) -> bool {
+    const KEYWORDS: &[&str] = &["const", "enum", "extern", "fn", "impl",
+        "let", "macro_rules", "mod", "pub", "static", "struct", "trait",
+        "type", "union", "unsafe", "use"];
+    let code = code.trim_start();
+    if code.starts_with('#') {
+        return true
+    }
+    let word = code.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .next()
+        .unwrap_or("");
+    // `unsafe { .. }` is an expression.
+    if word == "unsafe" {
+        return !code["unsafe".len()..].trim_start().starts_with('{')
+    }
+    KEYWORDS.contains(&word)
+}
@@ -0,0 +1,92 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![cfg(feature = "tokio")]
+#![deny(warnings)]
+
+extern crate everust;
+extern crate tempdir;
+extern crate tokio;
+
+use everust::{EvalError, Evaluator, Phase, RunOptions};
+use std::time::Duration;
+use tempdir::TempDir;
+
+#[tokio::test]
+async fn eval_async_returns_value() {
+    let output = tokio::spawn(everust::eval_async(r#"println!("hi"); 1 + 1"#))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!("2", output.value);
+    assert_eq!("hi\n", output.stdout);
+}
+
+#[tokio::test]
+async fn async_build_failure_reports_diagnostics() {
+    match Evaluator::new().eval_async("let x: u32 = \"a\"; x").await {
+        Err(EvalError::Build(ref diagnostics)) if !diagnostics.is_empty() => {}
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[tokio::test]
+async fn async_infinite_loop_times_out() {
+    let evaluator = Evaluator::builder()
+        .run_timeout(Duration::from_millis(200))
+        .build();
+    match evaluator.eval_async("loop {}").await {
+        Err(EvalError::Timeout {phase: Phase::Run, elapsed}) => {
+            assert!(elapsed >= Duration::from_millis(200));
+        }
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn dropping_future_kills_program() {
+    let dir = TempDir::new("everust-test").unwrap();
+    let pid_path = dir.path().join("pid");
+    let code = r#"
+        let child = std::process::Command::new("sleep").arg("1000").spawn()
+            .unwrap();
+        let path = std::env::var("EVERUST_PID_FILE").unwrap();
+        std::fs::write(format!("{}.tmp", path), child.id().to_string())
+            .unwrap();
+        std::fs::rename(format!("{}.tmp", path), &path).unwrap();
+        loop {}
+    "#;
+    let compiled = Evaluator::new().compile_async(code).await.unwrap();
+    let options = RunOptions::new().env("EVERUST_PID_FILE", &pid_path);
+    let run = compiled.run_with_async(&options);
+    tokio::select! {
+        r = run => panic!("Unexpected result: {:?}", r),
+        _ = wait_for_file(&pid_path) => {}
+    }
+    let pid = std::fs::read_to_string(&pid_path).unwrap();
+    for _ in 0..500 {
+        if !is_running(&pid) {
+            return
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("Process {} is still running", pid);
+}
+
+#[cfg(target_os = "linux")]
+async fn wait_for_file(path: &std::path::Path) {
+    while !path.exists() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn is_running(pid: &str) -> bool {
+    match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        Ok(stat) => {
+            let state = stat.rsplit(") ").next().unwrap_or("");
+            !state.starts_with('Z')
+        }
+        Err(_) => false,
+    }
+}
@@ -1,6 +1,7 @@
 // Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
 
 #![deny(warnings)]
+#![allow(clippy::match_like_matches_macro)]
 
 extern crate everust;
 
@@ -18,11 +19,20 @@ fn eval_invalid() {
 
 #[test]
 fn eval_number() {
-    assert_eq!("4", eval("2 + 2").unwrap());
+    assert_eq!("4", eval("2 + 2").unwrap().value);
 }
 
 #[test]
 fn eval_string() {
     let code = r##"let s = "Hello, ".to_string(); s + "World!""##;
-    assert_eq!(r##""Hello, World!""##, eval(code).unwrap());
+    assert_eq!(r##""Hello, World!""##, eval(code).unwrap().value);
+}
+
+#[test]
+fn value_is_separate_from_output() {
+    let output = eval(r#"println!("hi"); eprint!("oops"); 3"#).unwrap();
+    assert_eq!("3", output.value);
+    assert_eq!("hi\n", output.stdout);
+    assert_eq!("oops", output.stderr);
+    assert!(output.status.success());
 }
@@ -0,0 +1,73 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![deny(warnings)]
+
+extern crate everust;
+
+use everust::{Bindings, EvalError, Evaluator};
+
+#[test]
+fn bound_variables_have_declared_types() {
+    let bindings = Bindings::new().bind("x", 21i64);
+    let evaluator = Evaluator::new();
+    let output = evaluator.eval_with_bindings("x * 2", &bindings).unwrap();
+    assert_eq!("42", output.value);
+    match evaluator.eval_with_bindings("let y: i32 = x; y", &bindings) {
+        Err(EvalError::Build(_)) => {}
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn strings_are_escaped() {
+    let s = "quote \" backslash \\ newline \n \"); panic!(\"injected";
+    let bindings = Bindings::new()
+        .bind("s", s.to_string())
+        .bind("r", "static")
+        .bind("c", '\'');
+    let output = Evaluator::new()
+        .eval_with_bindings("(s, r, c)", &bindings)
+        .unwrap();
+    assert_eq!(format!("{:?}", (s, "static", '\'')), output.value);
+}
+
+#[test]
+fn nested_values_round_trip() {
+    let v = vec![Some((1u8, "a".to_string())), None];
+    let a = [-1.5f64, f64::INFINITY, f64::NEG_INFINITY];
+    let bindings = Bindings::new()
+        .bind("v", v.clone())
+        .bind("a", a)
+        .bind("n", f32::NAN)
+        .bind("u", (u128::MAX, i128::MIN, ()));
+    let output = Evaluator::new()
+        .eval_with_bindings("(v, a, n.is_nan(), u)", &bindings)
+        .unwrap();
+    let expected = (v, a, true, (u128::MAX, i128::MIN, ()));
+    assert_eq!(format!("{:?}", expected), output.value);
+}
+
+#[test]
+fn invalid_binding_name_is_rejected() {
+    for name in &["", "1x", "a b", "fn", "x; panic!()"] {
+        let bindings = Bindings::new().bind(*name, 1);
+        match Evaluator::new().eval_with_bindings("1", &bindings) {
+            Err(EvalError::Build(ref diagnostics)) => {
+                assert!(diagnostics[0].message.contains("Invalid binding"));
+            }
+            r => panic!("Unexpected result for {:?}: {:?}", name, r),
+        }
+    }
+}
+
+#[test]
+fn bindings_do_not_shift_snippet_locations() {
+    let bindings = Bindings::new().bind("x", 1).bind("y", 2);
+    match Evaluator::new().eval_with_bindings("x + z", &bindings) {
+        Err(EvalError::Build(ref diagnostics)) => {
+            let rendered = diagnostics[0].to_string();
+            assert!(rendered.contains("<snippet>:1:5"), "{}", rendered);
+        }
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
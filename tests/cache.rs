@@ -0,0 +1,115 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![deny(warnings)]
+
+extern crate everust;
+extern crate tempdir;
+
+use everust::{Cache, Cargo, Dependency, Edition, Evaluator};
+use std::fs;
+use std::path::Path;
+use std::thread;
+use tempdir::TempDir;
+
+fn entry_count(dir: &Path) -> usize {
+    fs::read_dir(dir).unwrap()
+        .filter(|e| !e.as_ref().unwrap().file_name().to_string_lossy()
+            .starts_with('.'))
+        .count()
+}
+
+#[test]
+fn same_code_is_cached() {
+    let temp = TempDir::new("everust-test").unwrap();
+    let evaluator = Evaluator::builder()
+        .cache(Cache::new(temp.path()))
+        .build();
+    let output = evaluator.eval("1 + 1").unwrap();
+    assert!(!output.cached);
+    let output = evaluator.eval("1 + 1").unwrap();
+    assert!(output.cached);
+    assert_eq!("2", output.value);
+    assert!(!evaluator.eval("1 + 2").unwrap().cached);
+    assert_eq!(2, entry_count(temp.path()));
+}
+
+#[test]
+fn options_are_part_of_key() {
+    let temp = TempDir::new("everust-test").unwrap();
+    let code = "cfg!(everust_test)";
+    let plain = Evaluator::builder()
+        .cache(Cache::new(temp.path()))
+        .build();
+    assert_eq!("false", plain.eval(code).unwrap().value);
+    let with_cfg = Evaluator::builder()
+        .cfg("everust_test")
+        .cache(Cache::new(temp.path()))
+        .build();
+    let output = with_cfg.eval(code).unwrap();
+    assert!(!output.cached);
+    assert_eq!("true", output.value);
+}
+
+#[test]
+fn local_crates_are_part_of_key() {
+    let temp = TempDir::new("everust-test").unwrap();
+    let write_helper = |dir: &str, lib: &str| {
+        let dir = temp.path().join(dir).join("helper");
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("Cargo.toml"), "[package]\nname = \"helper\"\n\
+            version = \"0.1.0\"\n").unwrap();
+        fs::write(dir.join("src").join("lib.rs"), lib).unwrap();
+    };
+    write_helper("a", "pub fn answer() -> u32 { 1 }");
+    write_helper("b", "pub fn answer() -> u32 { 2 }");
+    let eval = |dir: &str| {
+        let cargo = Cargo::new().offline(true)
+            .dependency(Dependency::path("helper", "helper"));
+        Evaluator::builder()
+            .edition(Edition::E2018)
+            .cargo(cargo)
+            .current_dir(temp.path().join(dir))
+            .cache(Cache::new(temp.path().join("cache")))
+            .build()
+            .eval("helper::answer()")
+            .unwrap()
+    };
+    assert_eq!("1", eval("a").value);
+    assert!(eval("a").cached);
+    let output = eval("b");
+    assert!(!output.cached);
+    assert_eq!("2", output.value);
+    write_helper("a", "pub fn answer() -> u32 { 10 }");
+    let output = eval("a");
+    assert!(!output.cached);
+    assert_eq!("10", output.value);
+}
+
+#[test]
+fn cache_size_is_limited() {
+    let temp = TempDir::new("everust-test").unwrap();
+    let evaluator = Evaluator::builder()
+        .cache(Cache::new(temp.path()).max_size(1))
+        .build();
+    assert_eq!("2", evaluator.eval("1 + 1").unwrap().value);
+    assert_eq!(0, entry_count(temp.path()));
+    assert!(!evaluator.eval("1 + 1").unwrap().cached);
+}
+
+#[test]
+fn cache_can_be_shared() {
+    let temp = TempDir::new("everust-test").unwrap();
+    let threads = (0..4)
+        .map(|_| {
+            let cache = Cache::new(temp.path()).max_size(1 << 40);
+            thread::spawn(move || {
+                let evaluator = Evaluator::builder().cache(cache).build();
+                evaluator.eval("vec![1, 2]").unwrap().value
+            })
+        })
+        .collect::<Vec<_>>();
+    for t in threads {
+        assert_eq!("[1, 2]", t.join().unwrap());
+    }
+    assert_eq!(1, entry_count(temp.path()));
+}
@@ -0,0 +1,45 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![deny(warnings)]
+
+extern crate everust;
+
+use everust::{EvalError, Evaluator};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn spawned_eval_returns_value() {
+    let handle = everust::spawn_eval("1 + 1");
+    assert_eq!("2", handle.join().unwrap().value);
+}
+
+#[test]
+fn cancelling_kills_running_program() {
+    let handle = Evaluator::new().spawn_eval(r#"
+        std::process::Command::new("sleep").arg("1000").spawn().unwrap();
+        loop {}
+    "#);
+    // Wait for the build to finish.
+    thread::sleep(Duration::from_secs(2));
+    assert!(!handle.is_finished());
+    let start = Instant::now();
+    handle.cancel();
+    match handle.join() {
+        Err(EvalError::Cancelled) => {}
+        r => panic!("Unexpected result: {:?}", r),
+    }
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn cancelling_from_another_thread_stops_build() {
+    let handle = Evaluator::new().spawn_eval("1 + 1");
+    let token = handle.cancel_token();
+    thread::spawn(move || token.cancel()).join().unwrap();
+    assert!(handle.cancel_token().is_cancelled());
+    match handle.join() {
+        Err(EvalError::Cancelled) => {}
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![deny(warnings)]
+
+extern crate everust;
+extern crate tempdir;
+
+use everust::{Cargo, Dependency, Edition, EvalError, Evaluator,
+    SNIPPET_FILE_NAME};
+use std::fs;
+use std::path::Path;
+use tempdir::TempDir;
+
+fn write_crate(dir: &Path, name: &str, lib: &str) {
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("Cargo.toml"), format!("[package]\nname = \"{}\"\n\
+        version = \"0.1.0\"\nedition = \"2018\"\n", name)).unwrap();
+    fs::write(dir.join("src").join("lib.rs"), lib).unwrap();
+}
+
+fn evaluator(cargo: Cargo) -> Evaluator {
+    Evaluator::builder()
+        .edition(Edition::E2018)
+        .cargo(cargo.offline(true))
+        .build()
+}
+
+#[test]
+fn path_dependency_can_be_used() {
+    let temp = TempDir::new("everust-test").unwrap();
+    let dir = temp.path().join("helper");
+    write_crate(&dir, "helper", "pub fn answer() -> u32 { 42 }");
+    let cargo = Cargo::new().dependency(Dependency::path("helper", &dir));
+    let output = evaluator(cargo).eval("helper::answer()").unwrap();
+    assert_eq!("42", output.value);
+}
+
+#[test]
+fn relative_path_dependency_is_resolved() {
+    let temp = TempDir::new("everust-test").unwrap();
+    write_crate(&temp.path().join("helper"), "helper",
+        "pub fn answer() -> u32 { 42 }");
+    let evaluator = Evaluator::builder()
+        .edition(Edition::E2018)
+        .cargo(Cargo::new().offline(true)
+            .dependency(Dependency::path("helper", "helper")))
+        .current_dir(temp.path())
+        .build();
+    assert_eq!("42", evaluator.eval("helper::answer()").unwrap().value);
+    let evaluator = Evaluator::builder()
+        .header_dependencies(true)
+        .current_dir(temp.path())
+        .build();
+    let code = "---\n[dependencies]\nhelper = { path = \"./helper\" }\n---\n\
+        helper::answer()";
+    assert_eq!("42", evaluator.eval(code).unwrap().value);
+}
+
+#[test]
+fn vendored_dependency_can_be_used() {
+    let temp = TempDir::new("everust-test").unwrap();
+    let vendor_dir = temp.path().join("vendor");
+    let dir = vendor_dir.join("vendored");
+    write_crate(&dir, "vendored", r#"
+        #[cfg(feature = "loud")]
+        pub fn greet() -> &'static str { "HELLO" }
+    "#);
+    fs::write(dir.join("Cargo.toml"), "[package]\nname = \"vendored\"\n\
+        version = \"0.1.0\"\n[features]\nloud = []\n").unwrap();
+    fs::write(dir.join(".cargo-checksum.json"),
+        r#"{"files": {}, "package": null}"#).unwrap();
+    let cargo = Cargo::new()
+        .vendor_dir(&vendor_dir)
+        .dependency(Dependency::new("vendored", "0.1").feature("loud"));
+    let output = evaluator(cargo).eval("vendored::greet()").unwrap();
+    assert_eq!(r#""HELLO""#, output.value);
+}
+
+#[test]
+fn build_errors_refer_to_snippet() {
+    let temp = TempDir::new("everust-test").unwrap();
+    let dir = temp.path().join("helper");
+    write_crate(&dir, "helper", "");
+    let cargo = Cargo::new().dependency(Dependency::path("helper", &dir));
+    match evaluator(cargo).eval("let a = 1;\nhelper::missing()") {
+        Err(EvalError::Build(diagnostics)) => {
+            let span = diagnostics[0].primary_spans().next().unwrap();
+            assert_eq!(SNIPPET_FILE_NAME, span.file_name);
+            assert_eq!(2, span.line_start);
+        }
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn missing_dependency_is_reported() {
+    let cargo = Cargo::new()
+        .dependency(Dependency::path("missing", "/nonexistent/everust"));
+    match evaluator(cargo).eval("1") {
+        Err(EvalError::Build(diagnostics)) => {
+            assert!(diagnostics[0].message.contains("missing"),
+                "{:?}", diagnostics);
+        }
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn type_of_dependency_value() {
+    let temp = TempDir::new("everust-test").unwrap();
+    let dir = temp.path().join("helper");
+    write_crate(&dir, "helper", "pub struct Answer(pub u32);\n\
+        pub fn answer() -> Answer { Answer(42) }");
+    let cargo = Cargo::new().dependency(Dependency::path("helper", &dir));
+    let evaluator = evaluator(cargo);
+    assert_eq!("Answer", evaluator.type_of("helper::answer()")
+        .unwrap());
+    match evaluator.type_of("helper::missing()") {
+        Err(EvalError::Build(_)) => {}
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
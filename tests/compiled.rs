@@ -0,0 +1,94 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![deny(warnings)]
+
+extern crate everust;
+
+use everust::{EvalError, Evaluator, RunOptions};
+use std::sync::Arc;
+use std::thread;
+
+#[test]
+fn program_can_run_with_different_inputs() {
+    let code = r#"
+        use std::io::Read;
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input).unwrap();
+        let args = std::env::args().skip(1).collect::<Vec<_>>();
+        (input, args, std::env::var("EVERUST_TEST_VAR").ok())
+    "#;
+    let compiled = Evaluator::new().compile(code).unwrap();
+    let output = compiled.run().unwrap();
+    assert_eq!(r#"("", [], None)"#, output.value);
+    let options = RunOptions::new()
+        .stdin("input")
+        .args(["a", "b"])
+        .env("EVERUST_TEST_VAR", "var");
+    let output = compiled.run_with(&options).unwrap();
+    assert_eq!(r#"("input", ["a", "b"], Some("var"))"#, output.value);
+}
+
+#[test]
+fn program_can_run_concurrently() {
+    let code = "std::env::args().nth(1).unwrap().parse::<u32>().unwrap() + 1";
+    let compiled = Arc::new(Evaluator::new().compile(code).unwrap());
+    let threads = (0..4u32)
+        .map(|n| {
+            let compiled = compiled.clone();
+            thread::spawn(move || {
+                let options = RunOptions::new().arg(n.to_string());
+                compiled.run_with(&options).unwrap().value
+            })
+        })
+        .collect::<Vec<_>>();
+    for (n, t) in threads.into_iter().enumerate() {
+        assert_eq!((n + 1).to_string(), t.join().unwrap());
+    }
+}
+
+#[test]
+fn failed_run_reports_error() {
+    let compiled = Evaluator::new().compile("std::env::args().nth(1).unwrap()")
+        .unwrap();
+    match compiled.run() {
+        Err(EvalError::ProgReturnedError(stderr)) => {
+            assert!(stderr.contains("<snippet>:1:"), "{}", stderr);
+        }
+        r => panic!("Unexpected result: {:?}", r),
+    }
+    let output = compiled.run_with(&RunOptions::new().arg("x")).unwrap();
+    assert_eq!(r#""x""#, output.value);
+}
+
+#[test]
+fn evaluator_run_options_apply_to_every_run() {
+    let options = RunOptions::new().stdin("default").arg("a");
+    let evaluator = Evaluator::builder().run_options(options).build();
+    let code = r#"
+        use std::io::Read;
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input).unwrap();
+        (input, std::env::args().skip(1).collect::<Vec<_>>())
+    "#;
+    assert_eq!(r#"("default", ["a"])"#, evaluator.eval(code).unwrap().value);
+    let compiled = evaluator.compile(code).unwrap();
+    let output = compiled.run_with(&RunOptions::new().stdin("x").arg("b"))
+        .unwrap();
+    assert_eq!(r#"("x", ["a", "b"])"#, output.value);
+}
+
+#[test]
+fn cleared_environment_only_has_set_variables() {
+    let code = r#"
+        let mut vars = std::env::vars().collect::<Vec<_>>();
+        vars.sort();
+        vars
+    "#;
+    let options = RunOptions::new().env_clear().env("B", "2");
+    let evaluator = Evaluator::builder()
+        .env("A", "1")
+        .run_options(options)
+        .build();
+    let output = evaluator.eval(code).unwrap();
+    assert_eq!(r#"[("A", "1"), ("B", "2")]"#, output.value);
+}
@@ -0,0 +1,135 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![deny(warnings)]
+
+extern crate everust;
+
+use everust::DebugValue;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+#[allow(dead_code)]
+#[derive(Debug)]
+struct Point {
+    x: f64,
+    y: Option<i32>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+enum Shape {
+    Empty,
+    Circle(Point, u32),
+    Poly { points: Vec<Point>, name: &'static str },
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+struct Unit;
+
+fn assert_round_trip<T: Debug>(value: T) {
+    let compact = format!("{:?}", value);
+    let parsed = DebugValue::parse(&compact).unwrap();
+    assert_eq!(compact, parsed.to_string());
+    let pretty = format!("{:#?}", value);
+    assert_eq!(parsed, DebugValue::parse(&pretty).unwrap());
+    assert_eq!(pretty, format!("{:#}", parsed));
+}
+
+#[test]
+fn std_values_round_trip() {
+    assert_round_trip(());
+    assert_round_trip((1,));
+    assert_round_trip((true, 'x', '\'', -3i64, u64::MAX));
+    assert_round_trip("quote \" backslash \\ newline \n nul \0 bell \u{7}");
+    assert_round_trip(vec![1.5, -0.0, 1e100, f64::INFINITY, f64::NEG_INFINITY]);
+    // NaN is not equal to itself, so only the output is compared.
+    let nan = DebugValue::parse(&format!("{:?}", f64::NAN)).unwrap();
+    assert_eq!("NaN", nan.to_string());
+    assert_round_trip(vec![Some(vec![1u8]), None]);
+    assert_round_trip(Ok::<_, String>(vec![Vec::<i32>::new()]));
+    let map = vec![("a", vec![1]), ("b", vec![])]
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+    assert_round_trip(map);
+    assert_round_trip(BTreeMap::<i32, i32>::new());
+    assert_round_trip((1..4).collect::<BTreeSet<_>>());
+}
+
+#[test]
+fn derived_values_round_trip() {
+    assert_round_trip(Unit);
+    assert_round_trip(Shape::Empty);
+    assert_round_trip(Shape::Circle(Point {x: 1.0, y: None}, 2));
+    assert_round_trip(Shape::Poly {
+        points: vec![Point {x: 0.5, y: Some(-1)}, Point {x: 2.0, y: None}],
+        name: "tri\tangle",
+    });
+}
+
+#[test]
+fn values_can_be_inspected() {
+    let value = DebugValue::parse(
+        "Poly { points: [Point { x: 0.5, y: Some(-1) }], name: \"t\" }")
+        .unwrap();
+    assert_eq!(Some("Poly"), value.name());
+    let point = value.field("points").and_then(|p| p.get(0)).unwrap();
+    assert_eq!(Some(&DebugValue::Float(0.5)), point.field("x"));
+    assert_eq!(Some(&DebugValue::TupleStruct {
+        name: "Some".to_owned(),
+        fields: vec![DebugValue::Int(-1)],
+    }), point.field("y"));
+    assert_eq!(Some(&DebugValue::Str("t".to_owned())), value.field("name"));
+}
+
+#[test]
+fn unknown_tokens_are_kept() {
+    let s = format!("[1.5s, {}]", u128::MAX);
+    let value = DebugValue::parse(&s).unwrap();
+    assert_eq!(DebugValue::List(vec![
+        DebugValue::Raw("1.5s".to_owned()),
+        DebugValue::Raw(u128::MAX.to_string()),
+    ]), value);
+}
+
+#[test]
+fn generic_names_are_kept() {
+    let value = DebugValue::parse(&format!("{:?}", PhantomData::<i32>))
+        .unwrap();
+    assert_eq!(DebugValue::Unit("PhantomData<i32>".to_owned()), value);
+    assert_round_trip(PhantomData::<fn(&str) -> Vec<(u8, bool)>>);
+    assert_round_trip(Some((PhantomData::<Vec<i32>>, 1)));
+}
+
+#[test]
+fn invalid_output_is_rejected() {
+    for s in &["", "[1, 2", "\"abc", "Foo { x 1 }", "(1) 2", "'ab'",
+        "Foo<i32"]
+    {
+        assert!(DebugValue::parse(s).is_err(), "{:?}", s);
+    }
+}
+
+#[test]
+fn deep_nesting_is_rejected() {
+    let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+    assert!(DebugValue::parse(&nested(256)).is_ok());
+    assert!(DebugValue::parse(&nested(257)).is_err());
+    for s in &["[".repeat(200_000), "Some(".repeat(200_000),
+        "{1: ".repeat(200_000), "A { a: ".repeat(200_000)]
+    {
+        let e = DebugValue::parse(s).unwrap_err();
+        assert!(e.to_string().starts_with("Value is nested too deeply"),
+            "{}", e);
+    }
+}
+
+#[test]
+fn evaluated_value_can_be_parsed() {
+    let output = everust::eval("vec![(1, \"a\")]").unwrap();
+    assert_eq!(DebugValue::List(vec![DebugValue::Tuple(vec![
+        DebugValue::Int(1),
+        DebugValue::Str("a".to_owned()),
+    ])]), output.parse_value().unwrap());
+}
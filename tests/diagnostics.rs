@@ -0,0 +1,42 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![deny(warnings)]
+
+extern crate everust;
+
+use everust::{eval, Diagnostic, EvalError, Level};
+
+fn build_errors(code: &str) -> Vec<Diagnostic> {
+    match eval(code) {
+        Err(EvalError::Build(diagnostics)) => diagnostics,
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn mismatched_types_are_reported() {
+    let diagnostics = build_errors(r#"let x: i32 = "a"; x"#);
+    let error = diagnostics.iter()
+        .find(|d| d.level == Level::Error && d.code.is_some())
+        .unwrap();
+    assert_eq!("E0308", error.code.as_ref().unwrap().code);
+    assert_eq!("mismatched types", error.message);
+    let span = error.primary_spans().next().unwrap();
+    assert_eq!(span.line_start, span.line_end);
+    assert!(span.column_start < span.column_end);
+    assert!(error.rendered.as_ref().unwrap().contains("mismatched types"));
+}
+
+#[test]
+fn suggestions_are_reported() {
+    let diagnostics = build_errors("let v = vec![1]; v.len");
+    assert!(diagnostics.iter().any(|d| !d.suggestions().is_empty()));
+}
+
+#[test]
+fn build_error_display_is_rendered() {
+    let error = eval(r#""blah" + 4"#).unwrap_err();
+    let message = error.to_string();
+    assert!(message.starts_with("Build failed"));
+    assert!(message.contains("error[E0369]"));
+}
@@ -0,0 +1,116 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![deny(warnings)]
+
+extern crate everust;
+
+use everust::{ChangeKind, DebugValue, Evaluator};
+
+fn parse(s: &str) -> DebugValue {
+    DebugValue::parse(s).unwrap()
+}
+
+fn paths(old: &str, new: &str) -> Vec<String> {
+    parse(old).diff(&parse(new)).changes().into_iter()
+        .map(|change| change.path)
+        .collect()
+}
+
+#[test]
+fn equal_values_have_empty_diff() {
+    let value = parse("Foo { a: [1, 2], b: {\"x\": Some(1)} }");
+    let diff = value.diff(&value);
+    assert!(diff.is_empty());
+    assert!(diff.changes().is_empty());
+    let expected = format!("{:#}", value).lines()
+        .map(|line| format!("  {}\n", line))
+        .collect::<String>();
+    assert_eq!(expected, diff.to_string());
+}
+
+#[test]
+fn changes_are_located_by_path() {
+    assert_eq!(vec![".points[1].x", ".name"], paths(
+        r#"Poly { points: [P { x: 1 }, P { x: 2 }], name: "a" }"#,
+        r#"Poly { points: [P { x: 1 }, P { x: 3 }], name: "b" }"#));
+    assert_eq!(vec![r#"["b"]"#], paths(r#"{"a": 1, "b": 2}"#,
+        r#"{"a": 1, "b": 3}"#));
+    assert_eq!(vec!["[0][1]"], paths("[(1, 2)]", "[(1, 3)]"));
+    assert_eq!(vec![""], paths("Some(1)", "None"));
+}
+
+#[test]
+fn fields_and_elements_are_added_and_removed() {
+    let old = parse("Foo { a: 1, b: {1, 2} }");
+    let new = parse("Foo { b: {2, 3}, c: 4 }");
+    let changes = old.diff(&new).changes();
+    assert_eq!(4, changes.len());
+    assert_eq!(".a", changes[0].path);
+    assert_eq!(ChangeKind::Removed(DebugValue::Int(1)), changes[0].kind);
+    assert_eq!(".b", changes[1].path);
+    assert_eq!(ChangeKind::Removed(DebugValue::Int(1)), changes[1].kind);
+    assert_eq!(".b", changes[2].path);
+    assert_eq!(ChangeKind::Added(DebugValue::Int(3)), changes[2].kind);
+    assert_eq!(".c", changes[3].path);
+    assert_eq!(ChangeKind::Added(DebugValue::Int(4)), changes[3].kind);
+}
+
+#[test]
+fn list_insertion_is_aligned() {
+    let old = parse("[1, 2, 3]");
+    let new = parse("[1, 5, 2, 3]");
+    let changes = old.diff(&new).changes();
+    assert_eq!(1, changes.len());
+    assert_eq!("[1]", changes[0].path);
+    assert_eq!(ChangeKind::Added(DebugValue::Int(5)), changes[0].kind);
+    assert_eq!("  [\n      1,\n+     5,\n      2,\n      3,\n  ]\n",
+        old.diff(&new).to_string());
+}
+
+#[test]
+fn removed_elements_have_old_indices() {
+    let old = parse("[0, 9, 1, (2, 3)]");
+    let new = parse("[1, (2, 4), 5]");
+    let changes = old.diff(&new).changes();
+    assert_eq!(vec!["[0]", "[1]", "[1][1]", "[2]"], changes.iter()
+        .map(|change| &change.path[..])
+        .collect::<Vec<_>>());
+    assert_eq!(ChangeKind::Removed(DebugValue::Int(0)), changes[0].kind);
+    assert_eq!(ChangeKind::Removed(DebugValue::Int(9)), changes[1].kind);
+    assert_eq!(ChangeKind::Added(DebugValue::Int(5)), changes[3].kind);
+}
+
+#[test]
+fn render_tree() {
+    let old = parse("Foo { a: 1, b: Bar(\"x\"), c: [Some(1)] }");
+    let new = parse("Foo { a: 1, b: Bar(\"y\"), c: [], d: () }");
+    let expected = "  Foo {
+      a: 1,
+      b: Bar(
+-         \"x\",
++         \"y\",
+      ),
+      c: [
+-         Some(
+-             1,
+-         ),
+      ],
++     d: (),
+  }
+";
+    assert_eq!(expected, old.diff(&new).to_string());
+}
+
+#[test]
+fn diff_eval_results() {
+    let evaluator = Evaluator::new();
+    let code = |n: u32| {
+        format!("(1..{}).map(|i| (i, i * i)).collect::<Vec<_>>()", n)
+    };
+    let old = evaluator.eval(&code(3)).unwrap().parse_value().unwrap();
+    let new = evaluator.eval(&code(4)).unwrap().parse_value().unwrap();
+    let changes = old.diff(&new).changes();
+    assert_eq!(1, changes.len());
+    assert_eq!("[2]", changes[0].path);
+    assert_eq!(ChangeKind::Added(parse("(3, 9)")), changes[0].kind);
+}
@@ -0,0 +1,70 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![deny(warnings)]
+
+extern crate everust;
+
+use everust::{EvalError, Evaluator};
+use std::collections::{BTreeMap, HashMap};
+
+#[test]
+fn values_are_decoded() {
+    let evaluator = Evaluator::new();
+    assert_eq!(3u8, evaluator.eval_as::<u8>("1 + 2").unwrap());
+    assert_eq!(-1.5, evaluator.eval_as::<f64>("-1.5").unwrap());
+    assert_eq!(None, evaluator.eval_as::<Option<f64>>("0.0 / 0.0").unwrap());
+    assert_eq!(u128::MAX, evaluator.eval_as::<u128>("u128::MAX").unwrap());
+    assert_eq!((true, 'x', ()),
+        evaluator.eval_as::<(bool, char, ())>("(true, 'x', ())").unwrap());
+    let s = evaluator
+        .eval_as::<String>(r#"format!("q\"b\\n\n\t\u{1}é{}", "")"#)
+        .unwrap();
+    assert_eq!("q\"b\\n\n\t\u{1}é", s);
+}
+
+#[test]
+fn collections_are_decoded() {
+    let evaluator = Evaluator::new();
+    let v = evaluator
+        .eval_as::<Vec<Option<(String, u32)>>>(
+            r#"vec![Some(("a".to_string(), 1)), None]"#)
+        .unwrap();
+    assert_eq!(vec![Some(("a".to_string(), 1)), None], v);
+    let code = r#"
+        let mut m = std::collections::HashMap::new();
+        m.insert("k", [1, 2]);
+        m
+    "#;
+    let m = evaluator.eval_as::<HashMap<String, Vec<i32>>>(code).unwrap();
+    assert_eq!(Some(&vec![1, 2]), m.get("k"));
+    let code = r#"
+        let mut m = std::collections::BTreeMap::new();
+        m.insert(2u32, Ok::<_, String>(false));
+        m.insert(1, Err("e".to_string()));
+        m
+    "#;
+    let m = evaluator.eval_as::<BTreeMap<u32, Result<bool, String>>>(code)
+        .unwrap();
+    let expected = vec![(1, Err("e".to_string())), (2, Ok(false))];
+    assert_eq!(expected, m.into_iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn mismatched_value_is_reported() {
+    match everust::eval_as::<u8>("300") {
+        Err(EvalError::ValueMismatch {value, ..}) => assert_eq!("300", value),
+        r => panic!("Unexpected result: {:?}", r),
+    }
+    match everust::eval_as::<String>("1") {
+        Err(EvalError::ValueMismatch {..}) => {}
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn unsupported_type_fails_to_build() {
+    match everust::eval_as::<u8>("struct S; S") {
+        Err(EvalError::Build(_)) => {}
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
@@ -0,0 +1,55 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![deny(warnings)]
+
+extern crate everust;
+
+use everust::{Edition, EvalError, Evaluator};
+
+#[test]
+fn edition_is_passed_to_rustc() {
+    let code = "let async = 1; async";
+    assert_eq!("1", Evaluator::new().eval(code).unwrap().value);
+    let evaluator = Evaluator::builder().edition(Edition::E2018).build();
+    match evaluator.eval(code) {
+        Err(EvalError::Build(_)) => {}
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn cfg_is_passed_to_rustc() {
+    let evaluator = Evaluator::builder().cfg("everust_test").build();
+    assert_eq!("true", evaluator.eval("cfg!(everust_test)").unwrap()
+        .value);
+}
+
+#[test]
+fn env_is_set_for_rustc_and_program() {
+    let evaluator = Evaluator::builder()
+        .env("EVERUST_TEST_VAR", "foo")
+        .build();
+    let code = r#"(env!("EVERUST_TEST_VAR"), std::env::var("EVERUST_TEST_VAR")
+        .unwrap())"#;
+    assert_eq!(r#"("foo", "foo")"#, evaluator.eval(code).unwrap().value);
+}
+
+#[test]
+fn current_dir_is_set_for_program() {
+    let dir = std::env::temp_dir().canonicalize().unwrap();
+    let evaluator = Evaluator::builder().current_dir(&dir).build();
+    let cwd = evaluator.eval("std::env::current_dir().unwrap()").unwrap()
+        .value;
+    assert_eq!(format!("{:?}", dir), cwd);
+}
+
+#[test]
+fn invalid_rustc_fails_to_spawn() {
+    let evaluator = Evaluator::builder()
+        .rustc("/nonexistent/everust-rustc")
+        .build();
+    match evaluator.eval("1") {
+        Err(EvalError::Other(_)) => {}
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![deny(warnings)]
+
+extern crate everust;
+extern crate tempdir;
+
+use everust::{eval, EvalError, Evaluator, Session, SNIPPET_FILE_NAME};
+use std::fs;
+use tempdir::TempDir;
+
+fn helper_crate() -> TempDir {
+    let temp = TempDir::new("everust-test").unwrap();
+    fs::create_dir(temp.path().join("src")).unwrap();
+    fs::write(temp.path().join("Cargo.toml"), "[package]\nname = \"helper\"\n\
+        version = \"0.1.0\"\n").unwrap();
+    fs::write(temp.path().join("src").join("lib.rs"),
+        "pub fn answer() -> u32 { 42 }").unwrap();
+    temp
+}
+
+fn with_dependencies() -> Evaluator {
+    Evaluator::builder().header_dependencies(true).build()
+}
+
+#[test]
+fn frontmatter_sets_edition() {
+    let code = "---\n[package]\nedition = \"2021\"\n---\n\
+        [1, 2].into_iter().collect::<Vec<i32>>()";
+    assert_eq!("[1, 2]", eval(code).unwrap().value);
+}
+
+#[test]
+fn doc_block_declares_dependencies() {
+    let helper = helper_crate();
+    let code = format!("//! Uses a helper.\n//! ```cargo\n//! [dependencies]\n\
+        //! helper = {{ path = {:?} }}\n//! ```\nhelper::answer()",
+        helper.path());
+    assert_eq!("42", with_dependencies().eval(&code).unwrap().value);
+}
+
+#[test]
+fn dependencies_are_rejected_by_default() {
+    let helper = helper_crate();
+    let code = format!("---\n[dependencies]\nhelper = {{ path = {:?} }}\n\
+        ---\nhelper::answer()", helper.path());
+    match eval(&code) {
+        Err(EvalError::Build(diagnostics)) => {
+            assert!(diagnostics[0].message.contains("header_dependencies"),
+                "{:?}", diagnostics);
+        }
+        r => panic!("Unexpected result: {:?}", r),
+    }
+    let mut session = Session::new();
+    assert!(session.eval(&code).is_err());
+    assert!(session.history().is_empty());
+}
+
+#[test]
+fn positions_are_preserved() {
+    let code = "---cargo\n[package]\nedition = \"2018\"\n---\n\
+        let a: u8 = \"a\";";
+    match eval(code) {
+        Err(EvalError::Build(diagnostics)) => {
+            let span = diagnostics[0].primary_spans().next().unwrap();
+            assert_eq!(SNIPPET_FILE_NAME, span.file_name);
+            assert_eq!((5, 13), (span.line_start, span.column_start));
+            let text = &code[span.byte_start as usize..span.byte_end as usize];
+            assert_eq!("\"a\"", text);
+        }
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn leading_dashes_are_code() {
+    assert_eq!("-5", eval("---5").unwrap().value);
+    assert_eq!("-1", eval("---1\n").unwrap().value);
+}
+
+#[test]
+fn invalid_header_is_reported() {
+    match eval("---\n[dependencies\n---\n1") {
+        Err(EvalError::Build(diagnostics)) => {
+            assert!(diagnostics[0].message.starts_with("Invalid header"),
+                "{:?}", diagnostics);
+        }
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn session_keeps_dependencies() {
+    let helper = helper_crate();
+    let mut session = Session::with_evaluator(with_dependencies());
+    let code = format!("---\n[dependencies]\nhelper = {{ path = {:?} }}\n\
+        ---\nlet a = helper::answer();", helper.path());
+    session.eval(&code).unwrap();
+    assert_eq!("43", session.eval("a + helper::answer() - 41").unwrap().value);
+}
@@ -0,0 +1,113 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![cfg(target_os = "linux")]
+#![deny(warnings)]
+
+extern crate everust;
+
+use everust::{EvalError, EvalOutput, Evaluator, Limit, ResourceLimits};
+use std::time::Duration;
+
+fn eval_with_limits(limits: ResourceLimits, code: &str)
+    -> Result<EvalOutput, EvalError>
+{
+    Evaluator::builder().resource_limits(limits).build().eval(code)
+}
+
+fn assert_exceeded(r: Result<EvalOutput, EvalError>, expected: Limit) {
+    match r {
+        Err(EvalError::ResourceLimitExceeded {limit, ..}) => {
+            assert_eq!(expected, limit);
+        }
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn memory_limit() {
+    let limits = ResourceLimits {
+        memory: Some(256 << 20),
+        ..ResourceLimits::default()
+    };
+    let output = eval_with_limits(limits, "vec![0u8; 3].len()").unwrap();
+    assert_eq!("3", output.value);
+    let r = eval_with_limits(limits, "vec![1u8; 1 << 30].len()");
+    assert_exceeded(r, Limit::Memory);
+}
+
+#[test]
+fn cpu_time_limit() {
+    let limits = ResourceLimits {
+        cpu_time: Some(Duration::from_secs(1)),
+        ..ResourceLimits::default()
+    };
+    assert_exceeded(eval_with_limits(limits, "loop {}"), Limit::CpuTime);
+}
+
+#[test]
+fn file_size_limit() {
+    let limits = ResourceLimits {
+        file_size: Some(1024),
+        ..ResourceLimits::default()
+    };
+    let code = r#"
+        let path = std::env::temp_dir()
+            .join(format!("everust-fsize-{}", std::process::id()));
+        let r = std::fs::write(&path, vec![0u8; 1 << 20]);
+        let _ = std::fs::remove_file(&path);
+        r
+    "#;
+    assert_exceeded(eval_with_limits(limits, code), Limit::FileSize);
+}
+
+#[test]
+fn open_files_limit() {
+    let limits = ResourceLimits {
+        open_files: Some(16),
+        ..ResourceLimits::default()
+    };
+    let code = r#"
+        let files = (0..32).map(|_| std::fs::File::open("/dev/null").unwrap())
+            .collect::<Vec<_>>();
+        files.len()
+    "#;
+    assert_exceeded(eval_with_limits(limits, code), Limit::OpenFiles);
+}
+
+#[test]
+fn processes_limit() {
+    let limits = ResourceLimits {
+        processes: Some(1),
+        ..ResourceLimits::default()
+    };
+    // The limit does not apply to root, so the program switches to nobody.
+    let code = r#"
+        extern "C" { fn setuid(uid: u32) -> i32; }
+        unsafe { setuid(65534); }
+        std::thread::spawn(|| ()).join().unwrap()
+    "#;
+    assert_exceeded(eval_with_limits(limits, code), Limit::Processes);
+}
+
+#[test]
+fn limits_are_not_detected_from_output_alone() {
+    let limits = ResourceLimits {
+        memory: Some(256 << 20),
+        processes: Some(4096),
+        open_files: Some(4096),
+        ..ResourceLimits::default()
+    };
+    let messages = r#"
+        eprintln!("memory allocation of 8 bytes failed");
+        eprintln!("failed to spawn thread: Os {{ code: 11, kind: WouldBlock, \
+            message: \"Resource temporarily unavailable\" }}");
+        eprintln!("Too many open files (os error 24)");
+    "#;
+    for end in &["panic!()", "std::process::exit(101)"] {
+        let code = format!("{}{}", messages, end);
+        match eval_with_limits(limits, &code) {
+            Err(EvalError::ProgReturnedError(_)) => {}
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![deny(warnings)]
+
+extern crate everust;
+
+use everust::{EvalError, Evaluator, SNIPPET_FILE_NAME};
+
+#[test]
+fn build_error_spans_refer_to_snippet() {
+    let code = "let a = 1;\nlet b: i32 = \"b\";\na + b";
+    let diagnostics = match everust::eval(code) {
+        Err(EvalError::Build(diagnostics)) => diagnostics,
+        r => panic!("Unexpected result: {:?}", r),
+    };
+    let span = diagnostics[0].primary_spans().next().unwrap();
+    assert_eq!(SNIPPET_FILE_NAME, span.file_name);
+    assert_eq!((2, 14), (span.line_start, span.column_start));
+    let text = &code[span.byte_start as usize..span.byte_end as usize];
+    assert_eq!("\"b\"", text);
+    let rendered = diagnostics[0].rendered.as_ref().unwrap();
+    assert!(rendered.contains("<snippet>:2:14"), "{}", rendered);
+    assert!(rendered.contains("\n2 | let b"), "{}", rendered);
+}
+
+#[test]
+fn panic_location_refers_to_snippet() {
+    match everust::eval("let v: Vec<i32> = Vec::new();\nv[3]") {
+        Err(EvalError::ProgReturnedError(stderr)) => {
+            assert!(stderr.contains("panicked at <snippet>:2:"), "{}",
+                stderr);
+        }
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn backtrace_hides_wrapper_frames() {
+    let evaluator = Evaluator::builder()
+        .codegen_option("debuginfo=2")
+        .env("RUST_BACKTRACE", "1")
+        .build();
+    let code = "struct S;\nimpl std::fmt::Debug for S {\n\
+        fn fmt(&self, _: &mut std::fmt::Formatter) -> std::fmt::Result {\n\
+        panic!()\n}\n}\nS";
+    match evaluator.eval(code) {
+        Err(EvalError::ProgReturnedError(stderr)) => {
+            assert!(stderr.contains("at <snippet>:4:1"), "{}", stderr);
+            assert!(!stderr.contains("main.rs"), "{}", stderr);
+        }
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
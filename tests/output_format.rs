@@ -0,0 +1,75 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![deny(warnings)]
+
+extern crate everust;
+
+use everust::{Bindings, Edition, EvalError, Evaluator, OutputFormat,
+    Session};
+
+fn eval(format: OutputFormat, code: &str) -> Result<String, EvalError> {
+    let evaluator = Evaluator::builder().output_format(format).build();
+    evaluator.eval(code).map(|output| output.value)
+}
+
+#[test]
+fn builtin_formats() {
+    let code = r#"("a", 1)"#;
+    assert_eq!(r#"("a", 1)"#, eval(OutputFormat::Debug, code).unwrap());
+    assert_eq!("(\n    \"a\",\n    1,\n)",
+        eval(OutputFormat::PrettyDebug, code).unwrap());
+    assert_eq!("a\"b", eval(OutputFormat::Display, r#""a\"b""#).unwrap());
+    assert_eq!("0x2a", eval(OutputFormat::Hex, "42").unwrap());
+    assert_eq!("0b101", eval(OutputFormat::Binary, "5u8").unwrap());
+}
+
+#[test]
+fn custom_format() {
+    let format = OutputFormat::Custom("[{:>6.2}]".to_owned());
+    assert_eq!("[  3.14]", eval(format, "std::f64::consts::PI").unwrap());
+    let format = OutputFormat::Custom("\"{}\"\n".to_owned());
+    assert_eq!("\"x\"\n", eval(format, "'x'").unwrap());
+}
+
+#[test]
+fn custom_format_requires_trait() {
+    let format = OutputFormat::Custom("{:x}".to_owned());
+    match eval(format, "1.5") {
+        Err(EvalError::Build(_)) => {}
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn format_applies_to_bindings_and_sessions() {
+    let evaluator = Evaluator::builder().output_format(OutputFormat::Hex)
+        .build();
+    let bindings = Bindings::new().bind("x", 16);
+    assert_eq!("0x20", evaluator.eval_with_bindings("x * 2", &bindings)
+        .unwrap().value);
+    let mut session = Session::new();
+    session.eval("let x = 255;").unwrap();
+    session.set_output_format(OutputFormat::Hex);
+    assert_eq!("0xff", session.eval("x").unwrap().value);
+}
+
+#[test]
+fn missing_trait_is_replaced_by_type_name() {
+    assert_eq!("main::main::Foo",
+        eval(OutputFormat::Debug, "struct Foo; Foo").unwrap());
+    assert_eq!("alloc::vec::Vec<i32>",
+        eval(OutputFormat::Display, "vec![1]").unwrap());
+    assert_eq!("f64", eval(OutputFormat::Binary, "1.5").unwrap());
+    let evaluator = Evaluator::builder().edition(Edition::E2015).build();
+    assert_eq!("main::main::Foo",
+        evaluator.eval("struct Foo; Foo").unwrap().value);
+}
+
+#[test]
+fn unit_is_empty() {
+    for format in [OutputFormat::Debug, OutputFormat::PrettyDebug,
+        OutputFormat::Display, OutputFormat::Hex]
+    {
+        assert_eq!("", eval(format, "let _x = 1;").unwrap());
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![cfg(target_os = "linux")]
+#![deny(warnings)]
+
+extern crate everust;
+
+use everust::{EvalError, EvalOutput, Evaluator, Limit, ResourceLimits,
+    Sandbox};
+use std::time::Duration;
+
+fn sandboxed(code: &str) -> Option<Result<EvalOutput, EvalError>> {
+    sandboxed_with(Evaluator::builder().sandbox(Sandbox::new()).build(), code)
+}
+
+fn sandboxed_with(evaluator: Evaluator, code: &str)
+    -> Option<Result<EvalOutput, EvalError>>
+{
+    match evaluator.eval(code) {
+        Err(EvalError::SandboxUnavailable(e)) => {
+            eprintln!("Skipping test as sandbox is unavailable: {}", e);
+            None
+        }
+        r => Some(r),
+    }
+}
+
+#[test]
+fn sandboxed_program_runs() {
+    if let Some(r) = sandboxed("vec![1, 2, 3].iter().sum::<i32>()") {
+        assert_eq!("6", r.unwrap().value);
+    }
+}
+
+#[test]
+fn host_files_are_hidden() {
+    let code = r#"std::path::Path::new("/etc/passwd").exists()"#;
+    if let Some(r) = sandboxed(code) {
+        assert_eq!("false", r.unwrap().value);
+    }
+}
+
+#[test]
+fn filesystem_is_read_only_except_work_dir() {
+    let code = r#"(std::fs::write("/usr/everust", "").is_err(),
+        std::fs::write("/everust", "").is_err(),
+        std::fs::write("/tmp/everust", "").is_ok(),
+        std::env::current_dir().unwrap())"#;
+    if let Some(r) = sandboxed(code) {
+        assert_eq!(r#"(true, true, true, "/tmp")"#, r.unwrap().value);
+    }
+}
+
+#[test]
+fn network_is_unavailable() {
+    let code = r#"std::net::TcpStream::connect("1.1.1.1:80").is_err()"#;
+    if let Some(r) = sandboxed(code) {
+        assert_eq!("true", r.unwrap().value);
+    }
+}
+
+#[test]
+fn program_is_in_new_pid_namespace() {
+    if let Some(r) = sandboxed("std::process::id()") {
+        assert_eq!("2", r.unwrap().value);
+    }
+}
+
+#[test]
+fn program_failure_is_reported() {
+    match sandboxed(r#"panic!("boom")"#) {
+        Some(Err(EvalError::ProgReturnedError(stderr))) => {
+            assert!(stderr.contains("boom"));
+        }
+        Some(r) => panic!("Unexpected result: {:?}", r),
+        None => {}
+    }
+}
+
+#[test]
+fn resource_limits_apply_in_sandbox() {
+    let evaluator = Evaluator::builder()
+        .sandbox(Sandbox::new())
+        .resource_limits(ResourceLimits {
+            cpu_time: Some(Duration::from_secs(1)),
+            ..ResourceLimits::default()
+        })
+        .build();
+    match sandboxed_with(evaluator, "loop {}") {
+        Some(Err(EvalError::ResourceLimitExceeded {limit, ..})) => {
+            assert_eq!(Limit::CpuTime, limit);
+        }
+        Some(r) => panic!("Unexpected result: {:?}", r),
+        None => {}
+    }
+}
+
+#[test]
+fn sandboxed_program_times_out() {
+    let evaluator = Evaluator::builder()
+        .sandbox(Sandbox::new())
+        .run_timeout(Duration::from_millis(300))
+        .build();
+    match sandboxed_with(evaluator, "loop {}") {
+        Some(Err(EvalError::Timeout {..})) | None => {}
+        Some(r) => panic!("Unexpected result: {:?}", r),
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![cfg(all(target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")))]
+#![deny(warnings)]
+
+extern crate everust;
+
+use everust::{EvalError, EvalOutput, Evaluator, RunOptions, Sandbox,
+    SeccompProfile};
+
+fn filtered(code: &str) -> Result<EvalOutput, EvalError> {
+    Evaluator::builder()
+        .seccomp(SeccompProfile::new())
+        .build()
+        .eval(code)
+}
+
+fn assert_blocked(r: Result<EvalOutput, EvalError>, expected: Option<&str>) {
+    match r {
+        Err(EvalError::SyscallBlocked {syscall, ..}) => {
+            if let Some(expected) = expected {
+                assert_eq!(Some(expected), syscall.as_ref().map(|s| &s[..]));
+            }
+        }
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn computation_is_allowed() {
+    let code = r#"
+        let v = (0..10).collect::<Vec<u64>>();
+        println!("{}", v.len());
+        eprintln!("done");
+        std::thread::spawn(move || v.iter().sum::<u64>()).join().unwrap()
+    "#;
+    let output = filtered(code).unwrap();
+    assert_eq!("45", output.value);
+    assert_eq!("10\n", output.stdout);
+    assert_eq!("done\n", output.stderr);
+}
+
+#[test]
+fn socket_is_blocked() {
+    let code = r#"std::net::TcpListener::bind("127.0.0.1:0")"#;
+    assert_blocked(filtered(code), Some("socket"));
+}
+
+#[test]
+fn process_creation_is_blocked() {
+    let code = r#"std::process::Command::new("true").status()"#;
+    assert_blocked(filtered(code), None);
+}
+
+#[test]
+fn execve_is_always_blocked() {
+    let evaluator = Evaluator::builder()
+        .seccomp(SeccompProfile::new().allow("execve").allow("execveat"))
+        .build();
+    let code = r#"
+        use std::os::unix::process::CommandExt;
+        std::process::Command::new("true").exec()
+    "#;
+    assert_blocked(evaluator.eval(code), Some("execve"));
+}
+
+#[test]
+fn filter_cannot_be_bypassed_by_the_program() {
+    let code = r#"
+        #[no_mangle]
+        pub extern "C" fn prctl(_: i32, _: u64, _: u64) -> i32 { 0 }
+        #[no_mangle]
+        pub extern "C" fn syscall(_: i64) -> i64 { 0 }
+        std::net::TcpListener::bind("127.0.0.1:0")
+    "#;
+    assert_blocked(filtered(code), Some("socket"));
+    let code = r#"
+        extern "C" fn before_main() {
+            let _ = std::net::UdpSocket::bind("127.0.0.1:0");
+        }
+        #[used]
+        #[link_section = ".init_array"]
+        static BEFORE_MAIN: extern "C" fn() = before_main;
+        1
+    "#;
+    assert_blocked(filtered(code), Some("socket"));
+}
+
+#[test]
+fn program_output_is_not_a_blocked_syscall() {
+    let code = r#"
+        eprintln!("everust: blocked syscall 41");
+        std::process::exit(159)
+    "#;
+    match filtered(code) {
+        Err(EvalError::ProgReturnedError(_)) => {}
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn allowed_syscall_can_be_forbidden() {
+    let evaluator = Evaluator::builder()
+        .seccomp(SeccompProfile::new().forbid("getcwd"))
+        .build();
+    let r = evaluator.eval("std::env::current_dir()");
+    assert_blocked(r, Some("getcwd"));
+}
+
+#[test]
+fn unknown_syscall_is_rejected() {
+    let evaluator = Evaluator::builder()
+        .seccomp(SeccompProfile::new().allow("not_a_syscall"))
+        .build();
+    match evaluator.eval("1") {
+        Err(EvalError::Other(_)) => {}
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn filter_applies_in_sandbox() {
+    let evaluator = Evaluator::builder()
+        .sandbox(Sandbox::new())
+        .seccomp(SeccompProfile::new())
+        .build();
+    let code = r#"std::net::TcpListener::bind("127.0.0.1:0")"#;
+    match evaluator.eval(code) {
+        Err(EvalError::SandboxUnavailable(_)) => {}
+        r => assert_blocked(r, Some("socket")),
+    }
+}
+
+#[test]
+fn cleared_environment_is_kept_with_filter() {
+    let evaluator = Evaluator::builder()
+        .seccomp(SeccompProfile::new())
+        .run_options(RunOptions::new().env_clear().env("A", "1"))
+        .build();
+    let code = "std::env::vars().map(|(k, _)| k).collect::<Vec<_>>()";
+    assert_eq!(r#"["A"]"#, evaluator.eval(code).unwrap().value);
+}
@@ -0,0 +1,71 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![deny(warnings)]
+
+extern crate everust;
+
+use everust::{EvalError, Session};
+
+#[test]
+fn bindings_are_kept() {
+    let mut session = Session::new();
+    assert_eq!("", session.eval("let x = 5;").unwrap().value);
+    assert_eq!("6", session.eval("let y = x + 1; y").unwrap().value);
+    assert_eq!("11", session.eval("x + y").unwrap().value);
+}
+
+#[test]
+fn items_are_kept() {
+    let mut session = Session::new();
+    session.eval("#[derive(Debug)]\nstruct Point { x: i32 }").unwrap();
+    session.eval("impl Point { fn twice(&self) -> i32 { self.x * 2 } }")
+        .unwrap();
+    assert_eq!("4", session.eval("Point { x: 2 }.twice()").unwrap().value);
+}
+
+#[test]
+fn trailing_value_is_not_moved() {
+    let mut session = Session::new();
+    let code = r#"let s = String::from("a;b // 'c'"); s"#;
+    assert_eq!(r#""a;b // 'c'""#, session.eval(code).unwrap().value);
+    assert_eq!("10", session.eval("s.len()").unwrap().value);
+}
+
+#[test]
+fn replayed_output_is_not_returned() {
+    let mut session = Session::new();
+    let output = session.eval(r#"println!("first"); eprintln!("first")"#)
+        .unwrap();
+    assert_eq!("first\n", output.stdout);
+    let output = session.eval(r#"println!("second"); 2"#).unwrap();
+    assert_eq!("second\n", output.stdout);
+    assert_eq!("", output.stderr);
+}
+
+#[test]
+fn output_looking_like_replay_marker_is_kept() {
+    let code = r#"print!("a\u{0}everust:replayed\u{0}b"); 1"#;
+    let expected = "a\u{0}everust:replayed\u{0}b";
+    assert_eq!(expected, everust::eval(code).unwrap().stdout);
+    let mut session = Session::new();
+    assert_eq!(expected, session.eval(code).unwrap().stdout);
+    assert_eq!(expected, session.eval(code).unwrap().stdout);
+}
+
+#[test]
+fn failed_evaluations_are_not_recorded() {
+    let mut session = Session::new();
+    session.eval("let a = 1;").unwrap();
+    match session.eval("let b: i32 = \"b\";") {
+        Err(EvalError::Build(_)) => {}
+        r => panic!("Unexpected result: {:?}", r),
+    }
+    match session.eval("let c = 1; panic!()") {
+        Err(EvalError::ProgReturnedError(_)) => {}
+        r => panic!("Unexpected result: {:?}", r),
+    }
+    assert_eq!(1, session.history().len());
+    assert_eq!("1", session.eval("a").unwrap().value);
+    session.clear();
+    assert!(session.eval("a").is_err());
+}
@@ -0,0 +1,54 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![deny(warnings)]
+
+extern crate everust;
+
+use everust::{Evaluator, OutputChunk, Stream};
+use std::time::{Duration, Instant};
+
+fn collect(chunks: &[OutputChunk], stream: Stream) -> String {
+    let data = chunks.iter()
+        .filter(|c| c.stream == stream)
+        .flat_map(|c| c.data.iter().cloned())
+        .collect::<Vec<_>>();
+    String::from_utf8(data).unwrap()
+}
+
+#[test]
+fn output_is_streamed_while_program_runs() {
+    let code = r#"
+        use std::io::Write;
+        println!("first");
+        std::io::stdout().flush().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        println!("second");
+        1
+    "#;
+    let mut first_seen = None;
+    let mut chunks = Vec::new();
+    let output = Evaluator::new().eval_streaming(code, |chunk| {
+        if first_seen.is_none() {
+            first_seen = Some(Instant::now());
+        }
+        chunks.push(chunk);
+    }).unwrap();
+    let first_seen = first_seen.expect("No output was streamed");
+    assert!(first_seen.elapsed() >= Duration::from_millis(400));
+    assert_eq!("1", output.value);
+    assert_eq!("first\nsecond\n", collect(&chunks, Stream::Stdout));
+    assert!(chunks.windows(2).all(|w| w[0].time <= w[1].time));
+}
+
+#[test]
+fn streamed_chunks_are_tagged_with_stream() {
+    let code = r#"print!("out"); eprint!("err"); 2"#;
+    let mut chunks = Vec::new();
+    let output = Evaluator::new()
+        .eval_streaming(code, |chunk| chunks.push(chunk))
+        .unwrap();
+    assert_eq!(output.stdout, collect(&chunks, Stream::Stdout));
+    assert_eq!(output.stderr, collect(&chunks, Stream::Stderr));
+    assert_eq!("out", output.stdout);
+    assert_eq!("err", output.stderr);
+}
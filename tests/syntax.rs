@@ -0,0 +1,28 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![deny(warnings)]
+
+extern crate everust;
+
+use everust::is_complete;
+
+#[test]
+fn balanced_code_is_complete() {
+    assert!(is_complete("fn f<'a>(s: &'a str) -> &'a str { s }"));
+    assert!(is_complete("let c = '{'; let d = '\\'';"));
+    assert!(is_complete("let s = r#\"{ \"# ; // {"));
+    assert!(is_complete("/* { /* } */ */ 1"));
+}
+
+#[test]
+fn unclosed_code_is_incomplete() {
+    assert!(!is_complete("let v = vec![1,"));
+    assert!(!is_complete("let s = \"{"));
+    assert!(!is_complete("let s = r#\"\"\"; {"));
+    assert!(!is_complete("/* /* */"));
+}
+
+#[test]
+fn mismatched_code_is_complete() {
+    assert!(is_complete("let v = (1, 2];"));
+}
@@ -0,0 +1,58 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![deny(warnings)]
+
+extern crate everust;
+
+use everust::{EvalError, Evaluator, Phase};
+use std::time::{Duration, Instant};
+
+#[test]
+fn infinite_loop_times_out() {
+    let evaluator = Evaluator::builder()
+        .run_timeout(Duration::from_millis(200))
+        .build();
+    match evaluator.eval("loop {}") {
+        Err(EvalError::Timeout {phase: Phase::Run, elapsed}) => {
+            assert!(elapsed >= Duration::from_millis(200));
+        }
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn slow_build_times_out() {
+    let evaluator = Evaluator::builder()
+        .compile_timeout(Duration::from_millis(1))
+        .build();
+    match evaluator.eval("1") {
+        Err(EvalError::Timeout {phase: Phase::Compile, ..}) => {}
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn spawned_processes_are_killed_on_timeout() {
+    let evaluator = Evaluator::builder()
+        .run_timeout(Duration::from_millis(200))
+        .build();
+    let code = r#"
+        std::process::Command::new("sleep").arg("1000").spawn().unwrap();
+        loop {}
+    "#;
+    let start = Instant::now();
+    match evaluator.eval(code) {
+        Err(EvalError::Timeout {phase: Phase::Run, ..}) => {}
+        r => panic!("Unexpected result: {:?}", r),
+    }
+    assert!(start.elapsed() < Duration::from_secs(60));
+}
+
+#[test]
+fn fast_program_does_not_time_out() {
+    let evaluator = Evaluator::builder()
+        .run_timeout(Duration::from_secs(60))
+        .build();
+    assert_eq!("2", evaluator.eval("1 + 1").unwrap().value);
+}
@@ -0,0 +1,56 @@
+// Copyright (C) 2017 Stephane Raux. Distributed under the MIT license.
+
+#![deny(warnings)]
+
+extern crate everust;
+
+use everust::{EvalError, Session, SNIPPET_FILE_NAME};
+
+#[test]
+fn eval_reports_type_name() {
+    let output = everust::eval("vec![1, 2]").unwrap();
+    assert_eq!("alloc::vec::Vec<i32>", output.type_name);
+    let output = everust::eval("let _x = 1;").unwrap();
+    assert_eq!("()", output.type_name);
+    let output = everust::eval("std::process::exit(0)").unwrap();
+    assert_eq!("", output.type_name);
+}
+
+#[test]
+fn type_of_does_not_run() {
+    let code = "println!(\"side effect\"); std::process::exit(1); \
+        (\"a\", 1.5f32)";
+    assert_eq!("(&str, f32)", everust::type_of(code).unwrap());
+}
+
+#[test]
+fn type_of_uses_inference_defaults() {
+    assert_eq!("i32", everust::type_of("1 + 2").unwrap());
+    assert_eq!("Option<f64>", everust::type_of("Some(1.5)").unwrap());
+    assert_eq!("()", everust::type_of("let _x = 1;").unwrap());
+}
+
+#[test]
+fn type_of_reports_errors() {
+    match everust::type_of("let x: u8 = \"a\"; 5u32") {
+        Err(EvalError::Build(diagnostics)) => {
+            assert!(diagnostics.iter()
+                .any(|d| d.message == "mismatched types"
+                    && d.spans[0].file_name == SNIPPET_FILE_NAME));
+        }
+        r => panic!("Unexpected result: {:?}", r),
+    }
+    match everust::type_of("undefined_function()") {
+        Err(EvalError::Build(_)) => {}
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn type_of_in_session() {
+    let mut session = Session::new();
+    session.eval("struct Point { x: u8 }\nlet p = Point { x: 1 };").unwrap();
+    assert_eq!("&Point", session.type_of("&p").unwrap());
+    assert_eq!("u8", session.type_of("p.x").unwrap());
+    assert_eq!(1, session.history().len());
+}